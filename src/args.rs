@@ -0,0 +1,324 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    crt::{CrtMask, CrtParams},
+    emu::{PowerOnRam, STATE_SLOTS, WINDOW_SCALE},
+    ntsc::NtscPreset,
+    palette::NtscParams,
+    region::Region,
+};
+
+/// Name of the FDS BIOS looked up next to the ROM and in the working directory
+const DEFAULT_BIOS_NAME: &str = "disksys.rom";
+
+/// Where the colors come from
+pub enum PaletteSource {
+    /// A 192 or 1536 byte .pal file
+    File(PathBuf),
+
+    /// The NTSC palette generator
+    Ntsc,
+}
+
+pub struct Args {
+    /// Path of the ROM or disk image
+    pub rom_path: PathBuf,
+
+    /// Path of the IPS/UPS/BPS patch
+    pub patch_path: Option<PathBuf>,
+
+    /// Path of the FDS BIOS
+    pub bios_path: Option<PathBuf>,
+
+    /// Overrides the region of the ROM, which otherwise comes from the NES 2.0 header or
+    /// defaults to NTSC
+    pub region: Option<Region>,
+
+    /// Contents of the internal RAM at power on
+    pub power_on_ram: PowerOnRam,
+
+    /// Replaces the built-in palette
+    pub palette: Option<PaletteSource>,
+
+    /// Hue, saturation, contrast and gamma of the NTSC palette and filter
+    pub ntsc_params: NtscParams,
+
+    /// Decodes the output as an NTSC signal
+    pub ntsc_filter: Option<NtscPreset>,
+
+    /// How many times the window and the CRT output are larger than 256x240
+    pub scale: u32,
+
+    /// Enables the CRT post-processing
+    pub crt: Option<CrtParams>,
+
+    /// Number of frames between two rewind snapshots
+    pub rewind_interval: Option<usize>,
+
+    /// Memory budget of the rewind buffer in MiB
+    pub rewind_budget: Option<usize>,
+
+    /// Path the input movie is recorded to, FM2 if it ends with .fm2
+    pub record_path: Option<PathBuf>,
+
+    /// Path of the native or FM2 movie to play back
+    pub play_path: Option<PathBuf>,
+
+    /// Save state slot loaded before the emulation starts
+    pub from_state: Option<u8>,
+
+    /// Runs without a window or audio output
+    pub headless: bool,
+
+    /// Number of frames to run in headless mode
+    pub frames: Option<usize>,
+
+    /// Path the last frame is written to as PNG in headless mode
+    pub screenshot_path: Option<PathBuf>,
+
+    /// Path the internal RAM is dumped to in headless mode
+    pub ram_path: Option<PathBuf>,
+
+    /// Expected hash of the last frame, a mismatch fails the headless run
+    pub expect_hash: Option<u32>,
+
+    /// Reference log the nestest.nes trace is compared against
+    pub nestest_log: Option<PathBuf>,
+
+    /// Prints the first use of every unofficial opcode
+    pub log_unofficial: bool,
+
+    /// Overrides the constant of the unstable XAA and LXA opcodes
+    pub unstable_magic: Option<u8>,
+
+    /// Opens the debugger console before the first instruction
+    pub debug: bool,
+
+    /// Test ROMs run with the $6000 status protocol instead of starting the emulator
+    pub test_roms: Vec<PathBuf>,
+
+    /// Per-opcode JSON test files or directories run against the CPU
+    pub cpu_tests: Vec<PathBuf>,
+}
+
+impl Args {
+    /// Returns the FDS BIOS path, falling back to disksys.rom next to the ROM or in the working directory
+    pub fn bios_path(&self) -> PathBuf {
+        if let Some(path) = &self.bios_path {
+            return path.clone();
+        }
+
+        let next_to_rom = self
+            .rom_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(DEFAULT_BIOS_NAME);
+
+        if next_to_rom.is_file() {
+            next_to_rom
+        } else {
+            PathBuf::from(DEFAULT_BIOS_NAME)
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: baroness [--bios disksys.rom] [--region ntsc|pal|dendy] [--ram-init zero|ones|alternating|random] [--palette file.pal|ntsc [--hue deg] [--saturation x] [--contrast x] [--gamma x]] [--ntsc-filter composite|svideo|rgb|monochrome] [--scale N] [--crt [--scanlines x] [--mask none|aperture|shadow] [--mask-strength x] [--bloom x] [--curvature x]] [--rewind-interval frames] [--rewind-budget MiB] [--record movie] [--play movie] [--from-state slot] [--headless --frames N [--screenshot png] [--dump-ram file] [--expect-hash crc32]] [--nestest nestest.log] [--log-unofficial] [--unstable-magic hex] [--debug] <rom> [patch]");
+    eprintln!("       baroness --test-roms [--frames N] <rom>...");
+    eprintln!("       baroness --cpu-tests <json file or directory>...");
+    std::process::exit(1);
+}
+
+pub fn parse_args() -> Args {
+    let mut positional = Vec::new();
+    let mut test_mode = false;
+    let mut cpu_test_mode = false;
+    let mut bios_path = None;
+    let mut region = None;
+    let mut power_on_ram = PowerOnRam::default();
+    let mut palette_path = None;
+    let mut ntsc_palette = false;
+    let mut ntsc_params = NtscParams::default();
+    let mut ntsc_filter = None;
+    let mut scale = WINDOW_SCALE;
+    let mut crt = false;
+    let mut crt_params = CrtParams::default();
+    let mut rewind_interval = None;
+    let mut rewind_budget = None;
+    let mut record_path = None;
+    let mut play_path = None;
+    let mut from_state = None;
+    let mut headless = false;
+    let mut frames = None;
+    let mut screenshot_path = None;
+    let mut ram_path = None;
+    let mut expect_hash = None;
+    let mut nestest_log = None;
+    let mut log_unofficial = false;
+    let mut unstable_magic = None;
+    let mut debug = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bios" => bios_path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--region" => {
+                let name = args.next().unwrap_or_else(|| usage());
+                region = Some(Region::from_name(&name).unwrap_or_else(|| usage()));
+            }
+            "--ram-init" => {
+                let name = args.next().unwrap_or_else(|| usage());
+                power_on_ram = PowerOnRam::from_name(&name).unwrap_or_else(|| usage());
+            }
+            "--palette" => {
+                let palette = args.next().unwrap_or_else(|| usage());
+                if palette == "ntsc" {
+                    ntsc_palette = true;
+                } else {
+                    palette_path = Some(PathBuf::from(palette));
+                }
+            }
+            "--ntsc-filter" => {
+                let name = args.next().unwrap_or_else(|| usage());
+                ntsc_filter = Some(NtscPreset::from_name(&name).unwrap_or_else(|| usage()));
+            }
+            "--scale" => {
+                let val = args.next().unwrap_or_else(|| usage());
+                scale = val
+                    .parse()
+                    .ok()
+                    .filter(|&scale| scale > 0)
+                    .unwrap_or_else(|| usage());
+            }
+            "--crt" => crt = true,
+            "--mask" => {
+                let name = args.next().unwrap_or_else(|| usage());
+                crt_params.mask = CrtMask::from_name(&name).unwrap_or_else(|| usage());
+            }
+            "--scanlines" | "--mask-strength" | "--bloom" | "--curvature" => {
+                let val = args.next().unwrap_or_else(|| usage());
+                let val = val.parse().unwrap_or_else(|_| usage());
+                match arg.as_str() {
+                    "--scanlines" => crt_params.scanlines = val,
+                    "--mask-strength" => crt_params.mask_strength = val,
+                    "--bloom" => crt_params.bloom = val,
+                    _ => crt_params.curvature = val,
+                }
+            }
+            "--hue" | "--saturation" | "--contrast" | "--gamma" => {
+                let val = args.next().unwrap_or_else(|| usage());
+                let val = val.parse().unwrap_or_else(|_| usage());
+                match arg.as_str() {
+                    "--hue" => ntsc_params.hue = val,
+                    "--saturation" => ntsc_params.saturation = val,
+                    "--contrast" => ntsc_params.contrast = val,
+                    _ => ntsc_params.gamma = val,
+                }
+            }
+            "--rewind-interval" => {
+                let frames = args.next().unwrap_or_else(|| usage());
+                rewind_interval = Some(frames.parse().unwrap_or_else(|_| usage()));
+            }
+            "--rewind-budget" => {
+                let mib = args.next().unwrap_or_else(|| usage());
+                rewind_budget = Some(mib.parse().unwrap_or_else(|_| usage()));
+            }
+            "--record" => record_path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--play" => play_path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--from-state" => {
+                let slot = args.next().unwrap_or_else(|| usage());
+                from_state = Some(
+                    slot.parse()
+                        .ok()
+                        .filter(|&slot| slot < STATE_SLOTS)
+                        .unwrap_or_else(|| usage()),
+                );
+            }
+            "--headless" => headless = true,
+            "--frames" => {
+                let count = args.next().unwrap_or_else(|| usage());
+                frames = Some(count.parse().unwrap_or_else(|_| usage()));
+            }
+            "--screenshot" => {
+                screenshot_path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage())))
+            }
+            "--dump-ram" => ram_path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--expect-hash" => {
+                let hash = args.next().unwrap_or_else(|| usage());
+                expect_hash = Some(u32::from_str_radix(&hash, 16).unwrap_or_else(|_| usage()));
+            }
+            "--test-roms" => test_mode = true,
+            "--cpu-tests" => cpu_test_mode = true,
+            "--nestest" => {
+                nestest_log = Some(PathBuf::from(args.next().unwrap_or_else(|| usage())))
+            }
+            "--log-unofficial" => log_unofficial = true,
+            "--debug" => debug = true,
+            "--unstable-magic" => {
+                let magic = args.next().unwrap_or_else(|| usage());
+                let magic = magic.trim_start_matches("0x");
+                unstable_magic = Some(u8::from_str_radix(magic, 16).unwrap_or_else(|_| usage()));
+            }
+            _ if arg.starts_with("--") => usage(),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+
+    // Outside of the test modes only a ROM and a patch are accepted
+    let many_files = test_mode || cpu_test_mode;
+    if positional.is_empty() || !many_files && positional.len() > 2 {
+        usage();
+    }
+    let rom_path = positional[0].clone();
+    let patch_path = positional.get(1).filter(|_| !many_files).cloned();
+    let test_roms = if test_mode {
+        positional.clone()
+    } else {
+        Vec::new()
+    };
+    let cpu_tests = if cpu_test_mode {
+        positional
+    } else {
+        Vec::new()
+    };
+
+    if headless && frames.is_none() {
+        usage();
+    }
+
+    let palette = match (palette_path, ntsc_palette) {
+        (Some(_), true) => usage(),
+        (Some(path), false) => Some(PaletteSource::File(path)),
+        (None, true) => Some(PaletteSource::Ntsc),
+        (None, false) => None,
+    };
+
+    Args {
+        rom_path,
+        patch_path,
+        bios_path,
+        region,
+        power_on_ram,
+        palette,
+        ntsc_params,
+        ntsc_filter,
+        scale,
+        crt: crt.then_some(crt_params),
+        rewind_interval,
+        rewind_budget,
+        record_path,
+        play_path,
+        from_state,
+        headless,
+        frames,
+        screenshot_path,
+        ram_path,
+        expect_hash,
+        nestest_log,
+        log_unofficial,
+        unstable_magic,
+        debug,
+        test_roms,
+        cpu_tests,
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{
+    emu::{EmulationFault, Emulator},
+    nes::NESFile,
+};
+
+/// Test status, written once the signature is in place
+const STATUS_ADDR: u16 = 0x6000;
+
+/// Signature marking the status and text as valid
+const SIGNATURE_ADDR: u16 = 0x6001;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+
+/// Null-terminated output text
+const TEXT_ADDR: u16 = 0x6004;
+
+/// End of the PRG RAM the text has to fit in
+const TEXT_END: u16 = 0x8000;
+
+/// The test is still running
+const STATUS_RUNNING: u8 = 0x80;
+
+/// The test asks for the reset button to be pressed
+const STATUS_NEEDS_RESET: u8 = 0x81;
+
+/// Frames to wait before pressing reset, the ROMs want at least 100 ms
+const RESET_DELAY_FRAMES: usize = 10;
+
+/// Frames a ROM may run before it's considered stuck, 5 minutes at 60 fps
+pub const DEFAULT_MAX_FRAMES: usize = 5 * 60 * 60;
+
+pub enum TestStatus {
+    Passed,
+    /// Result code written to $6000
+    Failed(u8),
+    /// The ROM didn't finish in time
+    TimedOut,
+    /// The ROM couldn't be run
+    Error(String),
+}
+
+pub struct TestResult {
+    pub status: TestStatus,
+    /// Text the ROM wrote to $6004
+    pub text: String,
+}
+
+impl TestResult {
+    pub fn error(message: String) -> TestResult {
+        TestResult {
+            status: TestStatus::Error(message),
+            text: String::new(),
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        matches!(self.status, TestStatus::Passed)
+    }
+}
+
+impl fmt::Display for TestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            TestStatus::Passed => write!(f, "passed")?,
+            TestStatus::Failed(code) => write!(f, "FAILED (code {})", code)?,
+            TestStatus::TimedOut => write!(f, "TIMED OUT")?,
+            TestStatus::Error(message) => write!(f, "ERROR: {}", message)?,
+        }
+
+        // The text usually repeats the test name and ends with the result
+        let text = self.text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() && !self.passed() {
+            write!(f, " - {}", text)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn has_signature(emu: &mut Emulator) -> bool {
+    (0..SIGNATURE.len() as u16)
+        .map(|off| emu.peek(SIGNATURE_ADDR + off))
+        .eq(SIGNATURE)
+}
+
+fn read_text(emu: &mut Emulator) -> String {
+    let bytes: Vec<u8> = (TEXT_ADDR..TEXT_END)
+        .map(|addr| emu.peek(addr))
+        .take_while(|&byte| byte != 0)
+        .collect();
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn run(emu: &mut Emulator, max_frames: usize) -> TestResult {
+    let mut reset_frame = None;
+
+    for frame in 0..max_frames {
+        // games and tests may read open bus, only a jam ends the test
+        if let Err(fault @ EmulationFault::CpuJammed { .. }) = emu.run_frame() {
+            return TestResult::error(fault.to_string());
+        }
+
+        if !has_signature(emu) {
+            continue;
+        }
+
+        match emu.peek(STATUS_ADDR) {
+            STATUS_RUNNING => {}
+            STATUS_NEEDS_RESET => {
+                if frame >= *reset_frame.get_or_insert(frame + RESET_DELAY_FRAMES) {
+                    emu.soft_reset();
+                    reset_frame = None;
+                }
+            }
+            code => {
+                return TestResult {
+                    status: if code == 0 {
+                        TestStatus::Passed
+                    } else {
+                        TestStatus::Failed(code)
+                    },
+                    text: read_text(emu),
+                }
+            }
+        }
+    }
+
+    TestResult {
+        status: TestStatus::TimedOut,
+        text: if has_signature(emu) {
+            read_text(emu)
+        } else {
+            String::new()
+        },
+    }
+}
+
+/// Runs a test ROM using the $6000 status protocol of blargg's tests
+/// https://github.com/christopherpow/nes-test-roms/blob/master/README.md
+pub fn run_test_rom(file: NESFile, max_frames: usize) -> TestResult {
+    // Unimplemented parts of the emulator panic, which shouldn't stop the other ROMs
+    panic::catch_unwind(AssertUnwindSafe(|| match Emulator::new(file) {
+        Ok(mut emu) => run(&mut emu, max_frames),
+        Err(err) => TestResult::error(err.to_string()),
+    }))
+    .unwrap_or_else(|_| TestResult::error("emulator crashed".to_string()))
+}
@@ -0,0 +1,317 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    emu::{BusAccess, Emulator, StatusRegister},
+    json::{parse_json, Value},
+};
+
+/// Results of one per-opcode JSON file
+/// https://github.com/SingleStepTests/65x02/tree/main/nes6502
+pub struct OpcodeReport {
+    pub name: String,
+    pub tests: usize,
+    /// Registers and memory match after the instruction
+    pub state_passed: usize,
+    /// The instruction took the expected number of cycles
+    pub cycles_passed: usize,
+    /// Every bus access matches, in order
+    pub bus_passed: usize,
+    /// Description of the first test whose state didn't match
+    pub first_failure: Option<String>,
+}
+
+impl OpcodeReport {
+    pub fn passed(&self) -> bool {
+        self.state_passed == self.tests
+            && self.cycles_passed == self.tests
+            && self.bus_passed == self.tests
+    }
+}
+
+impl fmt::Display for OpcodeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<8}{:>7}{:>7}{:>8}{:>7}",
+            self.name, self.tests, self.state_passed, self.cycles_passed, self.bus_passed
+        )
+    }
+}
+
+/// CPU registers in the test format
+struct CPUState {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+fn number(value: &Value, key: &str) -> Result<u64, ()> {
+    value.get(key).and_then(Value::as_u64).ok_or(())
+}
+
+fn parse_state(value: &Value) -> Result<CPUState, ()> {
+    let ram = value
+        .get("ram")
+        .and_then(Value::as_array)
+        .ok_or(())?
+        .iter()
+        .map(|entry| match entry.as_array() {
+            Some([addr, val]) => Ok((
+                addr.as_u64().ok_or(())? as u16,
+                val.as_u64().ok_or(())? as u8,
+            )),
+            _ => Err(()),
+        })
+        .collect::<Result<_, ()>>()?;
+
+    Ok(CPUState {
+        pc: number(value, "pc")? as u16,
+        s: number(value, "s")? as u8,
+        a: number(value, "a")? as u8,
+        x: number(value, "x")? as u8,
+        y: number(value, "y")? as u8,
+        p: number(value, "p")? as u8,
+        ram,
+    })
+}
+
+fn parse_cycles(value: &Value) -> Result<Vec<BusAccess>, ()> {
+    value
+        .as_array()
+        .ok_or(())?
+        .iter()
+        .map(|cycle| match cycle.as_array() {
+            Some([addr, val, kind]) => Ok(BusAccess {
+                addr: addr.as_u64().ok_or(())? as u16,
+                val: val.as_u64().ok_or(())? as u8,
+                write: kind.as_str().ok_or(())? == "write",
+            }),
+            _ => Err(()),
+        })
+        .collect()
+}
+
+fn set_state(emu: &mut Emulator, state: &CPUState) {
+    // every test starts on a fresh CPU, a KIL test would otherwise jam all the following ones
+    emu.clear_cpu_halt();
+    emu.clear_test_bus();
+    for &(addr, val) in &state.ram {
+        emu.poke_test_bus(addr, val);
+    }
+
+    emu.regs.pc = state.pc;
+    emu.regs.sp = state.s;
+    emu.regs.a = state.a;
+    emu.regs.x = state.x;
+    emu.regs.y = state.y;
+    emu.regs.flags = StatusRegister::from_bytes([state.p]);
+}
+
+/// Lists the differences between the emulator and the expected state
+fn compare_state(emu: &mut Emulator, state: &CPUState) -> Vec<String> {
+    let mut diffs = Vec::new();
+
+    let regs = [
+        ("pc", emu.regs.pc, state.pc),
+        ("s", emu.regs.sp as u16, state.s as u16),
+        ("a", emu.regs.a as u16, state.a as u16),
+        ("x", emu.regs.x as u16, state.x as u16),
+        ("y", emu.regs.y as u16, state.y as u16),
+        (
+            "p",
+            emu.regs.flags.clone().into_bytes()[0] as u16,
+            state.p as u16,
+        ),
+    ];
+    for (name, actual, expected) in regs {
+        if actual != expected {
+            diffs.push(format!("{}={:02X} expected {:02X}", name, actual, expected));
+        }
+    }
+
+    for &(addr, expected) in &state.ram {
+        let actual = emu.peek_test_bus(addr);
+        if actual != expected {
+            diffs.push(format!(
+                "[{:04X}]={:02X} expected {:02X}",
+                addr, actual, expected
+            ));
+        }
+    }
+
+    diffs
+}
+
+/// Runs every test of a per-opcode file
+pub fn run_test_file(emu: &mut Emulator, path: &Path) -> Result<OpcodeReport, ()> {
+    let data = fs::read(path).map_err(|_| ())?;
+    let json = parse_json(&data)?;
+    let tests = json.as_array().ok_or(())?;
+
+    let mut report = OpcodeReport {
+        name: path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        tests: tests.len(),
+        state_passed: 0,
+        cycles_passed: 0,
+        bus_passed: 0,
+        first_failure: None,
+    };
+
+    for test in tests {
+        let initial = parse_state(test.get("initial").ok_or(())?)?;
+        let expected = parse_state(test.get("final").ok_or(())?)?;
+        let cycles = parse_cycles(test.get("cycles").ok_or(())?)?;
+
+        set_state(emu, &initial);
+        emu.test_bus().log.clear();
+
+        let cycle_count = emu.step_instruction();
+
+        let diffs = compare_state(emu, &expected);
+        if diffs.is_empty() {
+            report.state_passed += 1;
+        } else if report.first_failure.is_none() {
+            let name = test.get("name").and_then(Value::as_str).unwrap_or("?");
+            report.first_failure = Some(format!("\"{}\": {}", name, diffs.join(", ")));
+        }
+
+        if cycle_count == cycles.len() {
+            report.cycles_passed += 1;
+        }
+
+        if emu.test_bus().log == cycles {
+            report.bus_passed += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Expands directories into the JSON files they contain
+pub fn collect_test_files(paths: &[impl AsRef<Path>]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                let mut dir_files: Vec<_> = entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                    .collect();
+                dir_files.sort();
+                files.extend(dir_files);
+            }
+            Err(_) => files.push(path.to_path_buf()),
+        }
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::emu::Emulator;
+
+    use super::{run_test_file, OpcodeReport};
+
+    /// KIL at $0200
+    const KIL_TEST: &str = r#"[{"name": "02 kil",
+        "initial": {"pc": 512, "s": 253, "a": 0, "x": 0, "y": 0, "p": 36, "ram": [[512, 2]]},
+        "final": {"pc": 513, "s": 253, "a": 0, "x": 0, "y": 0, "p": 36, "ram": [[512, 2]]},
+        "cycles": [[512, 2, "read"], [513, 0, "read"]]}]"#;
+
+    /// LDA #$42 at $0200
+    const LDA_TEST: &str = r#"[{"name": "a9 lda",
+        "initial": {"pc": 512, "s": 253, "a": 0, "x": 0, "y": 0, "p": 36,
+            "ram": [[512, 169], [513, 66]]},
+        "final": {"pc": 514, "s": 253, "a": 66, "x": 0, "y": 0, "p": 36,
+            "ram": [[512, 169], [513, 66]]},
+        "cycles": [[512, 169, "read"], [513, 66, "read"]]}]"#;
+
+    /// PHP at $0200 with P=$24, pushes $34
+    const PHP_TEST: &str = r#"[{"name": "08 php",
+        "initial": {"pc": 512, "s": 253, "a": 0, "x": 0, "y": 0, "p": 36,
+            "ram": [[512, 8], [513, 0]]},
+        "final": {"pc": 513, "s": 252, "a": 0, "x": 0, "y": 0, "p": 36,
+            "ram": [[509, 52], [512, 8], [513, 0]]},
+        "cycles": [[512, 8, "read"], [513, 0, "read"], [509, 52, "write"]]}]"#;
+
+    /// PLP at $0200 pulling $FF, which leaves bit 4 clear
+    const PLP_TEST: &str = r#"[{"name": "28 plp",
+        "initial": {"pc": 512, "s": 252, "a": 0, "x": 0, "y": 0, "p": 36,
+            "ram": [[509, 255], [512, 40], [513, 0]]},
+        "final": {"pc": 513, "s": 253, "a": 0, "x": 0, "y": 0, "p": 239,
+            "ram": [[509, 255], [512, 40], [513, 0]]},
+        "cycles": [[512, 40, "read"], [513, 0, "read"], [508, 0, "read"], [509, 255, "read"]]}]"#;
+
+    /// RTI at $0200 pulling $00 for P and $1234 for PC, which leaves bit 5 set
+    const RTI_TEST: &str = r#"[{"name": "40 rti",
+        "initial": {"pc": 512, "s": 250, "a": 0, "x": 0, "y": 0, "p": 36,
+            "ram": [[507, 0], [508, 52], [509, 18], [512, 64], [513, 0]]},
+        "final": {"pc": 4660, "s": 253, "a": 0, "x": 0, "y": 0, "p": 32,
+            "ram": [[507, 0], [508, 52], [509, 18], [512, 64], [513, 0]]},
+        "cycles": [[512, 64, "read"], [513, 0, "read"], [506, 0, "read"], [507, 0, "read"],
+            [508, 52, "read"], [509, 18, "read"]]}]"#;
+
+    /// Writes each test to its own file in a temporary directory and runs them in order
+    fn run_tests(name: &str, tests: &[(&str, &str)]) -> Vec<OpcodeReport> {
+        let dir =
+            std::env::temp_dir().join(format!("baroness-cputest-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut emu = Emulator::with_test_bus();
+        let reports = tests
+            .iter()
+            .map(|(file_name, json)| {
+                let path = dir.join(file_name);
+                fs::write(&path, json).unwrap();
+                run_test_file(&mut emu, &path).unwrap()
+            })
+            .collect();
+
+        fs::remove_dir_all(&dir).ok();
+        reports
+    }
+
+    #[test]
+    fn jam_does_not_leak_into_the_next_file() {
+        let reports = run_tests("jam", &[("02.json", KIL_TEST), ("a9.json", LDA_TEST)]);
+
+        assert!(reports[1].passed(), "{:?}", reports[1].first_failure);
+    }
+
+    #[test]
+    fn php_pushes_the_break_and_unused_bits() {
+        let reports = run_tests("php", &[("08.json", PHP_TEST)]);
+
+        assert!(reports[0].passed(), "{:?}", reports[0].first_failure);
+    }
+
+    #[test]
+    fn plp_ignores_the_break_bit() {
+        let reports = run_tests("plp", &[("28.json", PLP_TEST)]);
+
+        assert!(reports[0].passed(), "{:?}", reports[0].first_failure);
+    }
+
+    #[test]
+    fn rti_keeps_the_unused_bit_set() {
+        let reports = run_tests("rti", &[("40.json", RTI_TEST)]);
+
+        assert!(reports[0].passed(), "{:?}", reports[0].first_failure);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::emu::{ORIGINAL_HEIGHT, ORIGINAL_WIDTH};
+
+/// Radius in source pixels of the blur the bloom is made of
+const BLOOM_RADIUS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrtMask {
+    None,
+
+    /// Vertical red, green and blue stripes like a Trinitron
+    ApertureGrille,
+
+    /// Red, green and blue dots staggered between rows
+    ShadowMask,
+}
+
+impl CrtMask {
+    pub fn from_name(name: &str) -> Option<CrtMask> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(CrtMask::None),
+            "aperture" | "aperture-grille" => Some(CrtMask::ApertureGrille),
+            "shadow" | "shadow-mask" => Some(CrtMask::ShadowMask),
+            _ => None,
+        }
+    }
+
+    /// Which of the red, green and blue phosphors is at an output pixel
+    fn phosphor(&self, x: usize, y: usize) -> Option<usize> {
+        match self {
+            CrtMask::None => None,
+            CrtMask::ApertureGrille => Some(x % 3),
+            CrtMask::ShadowMask => Some((x + y % 2 * 2) % 3),
+        }
+    }
+}
+
+/// Strengths of the CRT effects, 0 disables an effect
+#[derive(Debug, Clone, Copy)]
+pub struct CrtParams {
+    /// How much the gaps between scanlines are darkened, 0.0-1.0
+    pub scanlines: f32,
+
+    pub mask: CrtMask,
+
+    /// How much the phosphors of the other colors are darkened, 0.0-1.0
+    pub mask_strength: f32,
+
+    /// How much of the blurred image is added back
+    pub bloom: f32,
+
+    /// Amount of barrel distortion
+    pub curvature: f32,
+}
+
+impl Default for CrtParams {
+    fn default() -> Self {
+        CrtParams {
+            scanlines: 0.5,
+            mask: CrtMask::ApertureGrille,
+            mask_strength: 0.3,
+            bloom: 0.15,
+            curvature: 0.0,
+        }
+    }
+}
+
+/// Where an output pixel takes its color from
+#[derive(Clone, Copy)]
+struct Sample {
+    /// Offsets of the two source pixels interpolated between
+    left: usize,
+    right: usize,
+    /// Weight of the right pixel
+    frac: f32,
+    /// Brightness of the beam at the distance from the middle of the line
+    beam: f32,
+}
+
+/// Post-processes frames on the CPU so the output doesn't depend on a GPU
+pub struct CrtFilter {
+    params: CrtParams,
+    width: usize,
+    height: usize,
+    /// Where every output pixel is on the source, the width from 0.0 to 1.0 and the height in
+    /// lines, None outside the curved screen
+    coords: Vec<Option<(f32, f32)>>,
+    /// `coords` resolved for the width of the last source
+    samples: Vec<Option<Sample>>,
+    samples_width: usize,
+    /// Blurred copy of the source as linear floats
+    bloom: Vec<f32>,
+    /// RGB24 image of the last processed frame
+    output: Box<[u8]>,
+}
+
+impl CrtFilter {
+    /// Output is `scale` times the original resolution, whatever the width of the source
+    pub fn new(params: CrtParams, scale: u32) -> CrtFilter {
+        let width = (ORIGINAL_WIDTH * scale) as usize;
+        let height = (ORIGINAL_HEIGHT * scale) as usize;
+
+        let mut coords = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                // -1.0 to 1.0 from the center of the screen
+                let u = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+
+                // https://en.wikipedia.org/wiki/Distortion_(optics)#Radial_distortion
+                let u = u * (1.0 + params.curvature * v * v);
+                let v = v * (1.0 + params.curvature * u * u);
+
+                if u.abs() > 1.0 || v.abs() > 1.0 {
+                    coords.push(None);
+                    continue;
+                }
+
+                coords.push(Some((
+                    (u + 1.0) / 2.0,
+                    (v + 1.0) / 2.0 * ORIGINAL_HEIGHT as f32,
+                )));
+            }
+        }
+
+        CrtFilter {
+            params,
+            width,
+            height,
+            coords,
+            samples: Vec::new(),
+            samples_width: 0,
+            bloom: Vec::new(),
+            output: vec![0; width * height * 3].into_boxed_slice(),
+        }
+    }
+
+    /// Processes an RGB24 frame of any width and `ORIGINAL_HEIGHT` lines
+    pub fn process(&mut self, src_width: u32, src: &[u8]) {
+        let src_width = src_width as usize;
+
+        if self.params.bloom > 0.0 {
+            self.blur(src_width, src);
+        }
+
+        if self.samples_width != src_width {
+            self.resolve_samples(src_width);
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let out = &mut self.output[(y * self.width + x) * 3..][..3];
+
+                let Some(sample) = self.samples[y * self.width + x] else {
+                    out.fill(0);
+                    continue;
+                };
+
+                let phosphor = self.params.mask.phosphor(x, y);
+
+                for (channel, val) in out.iter_mut().enumerate() {
+                    let a = src[sample.left + channel] as f32;
+                    let b = src[sample.right + channel] as f32;
+                    let mut color = (a + (b - a) * sample.frac) * sample.beam;
+
+                    if self.params.bloom > 0.0 {
+                        color += self.bloom[sample.left + channel] * self.params.bloom;
+                    }
+
+                    if phosphor.is_some_and(|phosphor| phosphor != channel) {
+                        color *= 1.0 - self.params.mask_strength;
+                    }
+
+                    *val = color.clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    }
+
+    /// Computes where the output pixels sample a source of the given width
+    fn resolve_samples(&mut self, src_width: usize) {
+        let src_height = ORIGINAL_HEIGHT as usize;
+
+        self.samples = self
+            .coords
+            .iter()
+            .map(|coords| {
+                let (sx, sy) = (*coords)?;
+
+                // interpolated between the two nearest source pixels
+                let sx = (sx * src_width as f32 - 0.5).max(0.0);
+                let left = (sx as usize).min(src_width - 1);
+                let right = (left + 1).min(src_width - 1);
+                let line = (sy as usize).min(src_height - 1);
+
+                // the beam is brightest in the middle of the line and fades towards the gaps
+                let dist = (sy.fract() - 0.5) * 2.0;
+
+                Some(Sample {
+                    left: (line * src_width + left) * 3,
+                    right: (line * src_width + right) * 3,
+                    frac: sx.fract(),
+                    beam: 1.0 - self.params.scanlines * dist * dist,
+                })
+            })
+            .collect();
+        self.samples_width = src_width;
+    }
+
+    /// Box blurs the source horizontally and vertically into `bloom`
+    fn blur(&mut self, width: usize, src: &[u8]) {
+        let height = ORIGINAL_HEIGHT as usize;
+        let taps = (BLOOM_RADIUS * 2 + 1) as f32;
+
+        let mut horizontal = vec![0.0; width * height * 3];
+        for y in 0..height {
+            for x in 0..width {
+                let (start, end) = (
+                    x.saturating_sub(BLOOM_RADIUS),
+                    (x + BLOOM_RADIUS).min(width - 1),
+                );
+                for channel in 0..3 {
+                    let sum: f32 = (start..=end)
+                        .map(|x| src[(y * width + x) * 3 + channel] as f32)
+                        .sum();
+                    horizontal[(y * width + x) * 3 + channel] = sum / taps;
+                }
+            }
+        }
+
+        self.bloom.resize(width * height * 3, 0.0);
+        for y in 0..height {
+            let (start, end) = (
+                y.saturating_sub(BLOOM_RADIUS),
+                (y + BLOOM_RADIUS).min(height - 1),
+            );
+            for x in 0..width {
+                for channel in 0..3 {
+                    let sum: f32 = (start..=end)
+                        .map(|y| horizontal[(y * width + x) * 3 + channel])
+                        .sum();
+                    self.bloom[(y * width + x) * 3 + channel] = sum / taps;
+                }
+            }
+        }
+    }
+
+    /// Width, height and RGB24 pixels of the last processed frame
+    pub fn output(&self) -> (u32, u32, &[u8]) {
+        (self.width as u32, self.height as u32, &self.output)
+    }
+}
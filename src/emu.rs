@@ -1,17 +1,59 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use modular_bitfield::{bitfield, specifiers::B1};
-use sdl2::{event::Event, render::Canvas, video::Window, EventPump};
+use sdl2::{event::Event, keyboard::Keycode};
 
 use crate::{
-    mapper::{get_mapper, Mapper},
+    checksum::{crc32, md5},
+    crt::{CrtFilter, CrtParams},
+    mapper::{get_mapper, Mapper, UnsupportedMapper},
+    movie::{COMMAND_POWER, COMMAND_SOFT_RESET},
     nes::NESFile,
+    ntsc::NtscFilter,
+    palette::Palette,
+    region::Region,
 };
 
-use self::{cpu::CPUData, ppu::PPUData};
+use self::{
+    audio::AudioData,
+    cpu::CPUData,
+    debugger::{Access, AddressSpace, Debugger},
+    display::Display,
+    input::InputData,
+    movie::MovieSession,
+    nsf::NSFPlayer,
+    ppu::PPUData,
+    rewind::RewindBuffer,
+    testbus::TestBus,
+};
 
+pub use self::{
+    fault::EmulationFault,
+    reset::PowerOnRam,
+    rewind::{DEFAULT_REWIND_BUDGET, DEFAULT_REWIND_INTERVAL},
+    savestate::STATE_SLOTS,
+    testbus::BusAccess,
+};
+
+mod audio;
 mod cpu;
+mod debugger;
+mod display;
+mod fault;
+mod input;
+mod movie;
+mod nsf;
+mod overlay;
 mod ppu;
+mod reset;
+mod rewind;
+mod savestate;
+mod testbus;
+mod trace;
 
 /// 2KiB internal memory
 const INTERNAL_RAM_SIZE: usize = usize::pow(2, 11);
@@ -22,15 +64,9 @@ pub const ORIGINAL_WIDTH: u32 = 256;
 /// Original window height
 pub const ORIGINAL_HEIGHT: u32 = 240;
 
-/// How many times should the original resolution(256x240) be scaled up
+/// How many times should the original resolution(256x240) be scaled up by default
 pub const WINDOW_SCALE: u32 = 4;
 
-/// Scaled window width
-pub const WINDOW_WIDTH: u32 = WINDOW_SCALE * ORIGINAL_WIDTH;
-
-/// Scaled window height
-pub const WINDOW_HEIGHT: u32 = WINDOW_SCALE * ORIGINAL_HEIGHT;
-
 #[bitfield]
 #[derive(Clone, Debug)]
 pub struct StatusRegister {
@@ -56,38 +92,123 @@ pub struct Registers {
 pub struct Emulator {
     pub regs: Registers,
     internal_ram: Box<[u8]>,
+    power_on_ram: PowerOnRam,
     cpu: CPUData,
     ppu: PPUData,
+    audio: AudioData,
+    input: InputData,
     mapper: Box<dyn Mapper>,
-    canvas: Canvas<Window>,
-    event_pump: EventPump,
+    nsf: Option<NSFPlayer>,
+    /// Lines of text drawn over the frame
+    overlay: Vec<String>,
+    display: Option<Display>,
     last_time: u128,
     frame_complete: bool,
     cycle_counter: usize,
+    region: Region,
+    palette: Palette,
+    /// Replaces the frame buffer with a filtered image when set
+    ntsc_filter: Option<NtscFilter>,
+    /// Post-processes the output when set
+    crt: Option<CrtFilter>,
+    window_scale: u32,
+    /// CRC-32 of the ROM contents, stored in save states
+    rom_crc: u32,
+    /// MD5 of the ROM contents, stored in movies
+    rom_md5: [u8; 16],
+    state_path: PathBuf,
+    state_slot: u8,
+    rewind: RewindBuffer,
+    /// Set while the rewind key is held
+    rewinding: bool,
+    movie: Option<MovieSession>,
+    /// Replaces the address space when testing the CPU
+    test_bus: Option<TestBus>,
+    /// First fault of the current frame
+    fault: Option<EmulationFault>,
+    /// Last fault shown to the user
+    last_fault: Option<EmulationFault>,
+    /// Unmapped accesses already logged, by bus, address and whether they were writes
+    logged_unmapped: HashSet<(AddressSpace, u16, bool)>,
+    /// Breakpoints and the console, None when not debugging
+    debugger: Option<Debugger>,
 }
 
 impl Emulator {
-    fn clock(&mut self) {
-        self.clock_ppu();
-        self.cycle_counter += 1;
-
-        if self.cycle_counter == 3 {
-            self.clock_cpu();
-            self.cycle_counter = 0;
-        }
+    /// Executes one instruction, the rest of the system runs along with its bus accesses
+    fn step_cpu(&mut self) {
+        self.step_instruction();
     }
 
     fn emulate(&mut self) {
-        const FRAME_TIME: u128 = 1_000_000_000 / 60;
+        let frame_time = self.region.frame_time();
 
-        self.reset();
+        self.nsf_start();
 
         let mut running = true;
         while running {
-            println!("EVENT PUMP");
-            for event in self.event_pump.poll_iter() {
+            let events: Vec<Event> = match &mut self.display {
+                Some(display) => display.event_pump.poll_iter().collect(),
+                None => Vec::new(),
+            };
+            for event in events {
                 match event {
                     Event::Quit { .. } => running = false,
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F6),
+                        ..
+                    } => self.mapper.switch_disk_side(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Right),
+                        ..
+                    } => self.nsf_next_track(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Left),
+                        ..
+                    } => self.nsf_previous_track(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F2),
+                        ..
+                    } => match self.save_state_slot() {
+                        Ok(()) => eprintln!("Saved state to slot {}", self.state_slot),
+                        Err(err) => eprintln!("Could not save state: {}", err),
+                    },
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F4),
+                        ..
+                    } => match self.load_state_slot() {
+                        Ok(()) => eprintln!("Loaded state from slot {}", self.state_slot),
+                        Err(err) => eprintln!("Could not load state: {}", err),
+                    },
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F3),
+                        ..
+                    } => self.queue_command(COMMAND_SOFT_RESET),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F5),
+                        ..
+                    } => self.queue_command(COMMAND_POWER),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F9),
+                        ..
+                    } => self.debug_break(),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::Backspace),
+                        ..
+                    } => self.rewinding = true,
+                    Event::KeyUp {
+                        keycode: Some(Keycode::Backspace),
+                        ..
+                    } => self.rewinding = false,
+                    Event::KeyDown {
+                        keycode: Some(keycode),
+                        ..
+                    } if (Keycode::Num0 as i32..=Keycode::Num9 as i32)
+                        .contains(&(keycode as i32)) =>
+                    {
+                        self.select_state_slot((keycode as i32 - Keycode::Num0 as i32) as u8);
+                        eprintln!("Selected save state slot {}", self.state_slot);
+                    }
                     _ => {}
                 };
             }
@@ -101,25 +222,147 @@ impl Emulator {
                     .as_nanos();
 
                 let elapsed = current_time - self.last_time;
-                println!("{} {}", elapsed, FRAME_TIME);
-                new_frame = elapsed > FRAME_TIME;
+                new_frame = elapsed > frame_time;
                 if new_frame {
                     self.last_time = current_time;
                 }
             }
 
-            while !self.frame_complete {
-                self.clock();
+            // While rewinding, every frame goes back one snapshot and runs a single frame to draw it
+            let rewound = self.rewinding && self.rewind_step();
+            if !rewound {
+                self.update_input();
             }
-            self.frame_complete = false;
+
+            if let Err(fault) = self.run_frame() {
+                self.report_fault(fault);
+            }
+
+            if !rewound {
+                self.record_rewind();
+            }
+        }
+    }
+
+    /// Runs until the PPU finishes the frame, returns the first fault that happened during it
+    pub fn run_frame(&mut self) -> Result<(), EmulationFault> {
+        while !self.frame_complete {
+            self.step_cpu();
+        }
+        self.frame_complete = false;
+        self.flush_audio();
+        self.nsf_frame();
+
+        match self.fault.take() {
+            Some(fault) => Err(fault),
+            None => Ok(()),
         }
     }
 
+    /// Opens the window and runs until it is closed
     pub fn start_emulation(&mut self) {
+        self.open_window();
         self.emulate();
     }
 
+    /// Runs the given number of frames without a window or audio output,
+    /// returns the first fault but keeps running after it
+    pub fn run_headless(&mut self, frames: usize) -> Result<(), EmulationFault> {
+        self.nsf_start();
+
+        let mut first_fault = None;
+        for _ in 0..frames {
+            self.update_input();
+            if let Err(fault) = self.run_frame() {
+                first_fault.get_or_insert(fault);
+            }
+        }
+
+        match first_fault {
+            Some(fault) => Err(fault),
+            None => Ok(()),
+        }
+    }
+
+    /// CRC-32 of the last frame
+    pub fn frame_hash(&self) -> u32 {
+        crc32(self.frame_buffer())
+    }
+
+    /// Replaces the palette the frame is rendered with
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    /// Enables the NTSC filter as the output stage
+    pub fn set_ntsc_filter(&mut self, filter: NtscFilter) {
+        self.ntsc_filter = Some(filter);
+    }
+
+    /// Sets how many times the window is larger than the original resolution
+    pub fn set_window_scale(&mut self, scale: u32) {
+        self.window_scale = scale;
+    }
+
+    /// Enables the CRT post-processing at the window scale
+    pub fn set_crt_filter(&mut self, params: CrtParams) {
+        self.crt = Some(CrtFilter::new(params, self.window_scale));
+    }
+
+    /// Contents of the 2KiB internal RAM
+    pub fn internal_ram(&self) -> &[u8] {
+        &self.internal_ram
+    }
+
+    /// Returns the cartridge data that has to persist between sessions
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        self.mapper.save_data()
+    }
+
+    /// Restores the cartridge data from a previous session
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        self.mapper.load_save_data(data);
+    }
+
+    /// Reads on the CPU bus, taking a CPU cycle
     pub fn read(&mut self, addr: u16) -> u8 {
+        self.tick();
+        let val = self.bus_read(addr);
+        self.debug_access(AddressSpace::Cpu, addr, Access::Read, val);
+        val
+    }
+
+    /// Writes on the CPU bus, taking a CPU cycle
+    pub fn write(&mut self, addr: u16, val: u8) {
+        self.tick();
+        self.debug_access(AddressSpace::Cpu, addr, Access::Write, val);
+        self.bus_write(addr, val);
+    }
+
+    /// Reads memory for debugging without taking a cycle or triggering read side effects
+    pub fn peek(&mut self, addr: u16) -> u8 {
+        if self.test_bus.is_some() {
+            return self.peek_test_bus(addr);
+        }
+
+        if addr < 0x2000 {
+            self.internal_ram[(addr & 0x7FF) as usize]
+        } else if addr < 0x4000 {
+            // the registers aren't read, only the value left on the PPU bus
+            self.ppu_io_latch()
+        } else if addr < 0x4020 {
+            // apu and io registers
+            0
+        } else {
+            self.mapper.peek_cpu(addr).unwrap_or((addr >> 8) as u8)
+        }
+    }
+
+    fn bus_read(&mut self, addr: u16) -> u8 {
+        if let Some(bus) = &mut self.test_bus {
+            return bus.read(addr);
+        }
+
         if addr < 0x2000 {
             // internal ram
             let off = addr & 0x7FF;
@@ -127,6 +370,8 @@ impl Emulator {
         } else if addr < 0x4000 {
             // ppu regs
             self.ppu_read_reg(addr as u8 % 8)
+        } else if addr == 0x4016 || addr == 0x4017 {
+            self.read_controller((addr - 0x4016) as usize)
         } else if addr < 0x4020 {
             // apu, io registers
             //todo!()
@@ -135,12 +380,27 @@ impl Emulator {
             // cartridge space
             match self.mapper.read_cpu(addr) {
                 Ok(val) => val,
-                Err(_) => panic!("Open bus"),
+                Err(err) => {
+                    self.raise_fault(EmulationFault::from_mapper_error(
+                        err,
+                        AddressSpace::Cpu,
+                        addr,
+                        None,
+                    ));
+
+                    // the bus still holds the high byte of the address in most cases
+                    (addr >> 8) as u8
+                }
             }
         }
     }
 
-    pub fn write(&mut self, addr: u16, val: u8) {
+    fn bus_write(&mut self, addr: u16, val: u8) {
+        if let Some(bus) = &mut self.test_bus {
+            bus.write(addr, val);
+            return;
+        }
+
         if addr < 0x2000 {
             // internal ram
             let off = addr & 0x7FF;
@@ -148,36 +408,39 @@ impl Emulator {
         } else if addr < 0x4000 {
             // ppu regs
             self.ppu_write_reg(addr as u8 % 8, val);
+        } else if addr == 0x4014 {
+            self.oam_dma(val);
+        } else if addr == 0x4016 {
+            self.write_controller_strobe(val);
         } else if addr < 0x4020 {
             // apu, io registers
             //todo!()
         } else {
             // cartridge space
-            self.mapper.write_cpu(addr, val).unwrap();
+            if let Err(err) = self.mapper.write_cpu(addr, val) {
+                self.raise_fault(EmulationFault::from_mapper_error(
+                    err,
+                    AddressSpace::Cpu,
+                    addr,
+                    Some(val),
+                ));
+            }
         }
     }
 
-    pub fn new(file: Vec<u8>, nes_file: NESFile) -> Emulator {
-        let sdl_context = sdl2::init().unwrap();
-        let video_subsystem = sdl_context.video().unwrap();
-
-        let window = video_subsystem
-            .window("baroness", WINDOW_WIDTH, WINDOW_HEIGHT)
-            .position_centered()
-            .build()
-            .unwrap();
+    pub fn new(nes_file: NESFile) -> Result<Emulator, UnsupportedMapper> {
+        let mapper = get_mapper(&nes_file)?;
 
-        let mut canvas = window.into_canvas().build().unwrap();
+        let rom_data = [
+            nes_file.prg_rom.as_slice(),
+            nes_file.chr_rom.as_slice(),
+            &nes_file.disk_sides.concat(),
+        ]
+        .concat();
 
-        canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 0, 0));
-        canvas.clear();
-        canvas.present();
-
-        let event_pump = sdl_context.event_pump().unwrap();
-
-        let mapper = get_mapper(&file, &nes_file);
-        Emulator {
+        let mut emu = Emulator {
             internal_ram: vec![0; INTERNAL_RAM_SIZE].into_boxed_slice(),
+            power_on_ram: PowerOnRam::default(),
             regs: Registers {
                 a: 0,
                 x: 0,
@@ -188,15 +451,38 @@ impl Emulator {
             },
             cpu: CPUData::new(),
             ppu: PPUData::new(),
+            audio: AudioData::new(),
+            input: InputData::new(),
             mapper,
-            canvas,
-            event_pump,
+            nsf: nes_file.nsf.map(NSFPlayer::new),
+            overlay: Vec::new(),
+            display: None,
             last_time: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_nanos(),
             frame_complete: false,
             cycle_counter: 0,
-        }
+            region: nes_file.region.unwrap_or_default(),
+            palette: Palette::default(),
+            ntsc_filter: None,
+            crt: None,
+            window_scale: WINDOW_SCALE,
+            rom_crc: crc32(&rom_data),
+            rom_md5: md5(&rom_data),
+            state_path: PathBuf::from("baroness"),
+            state_slot: 0,
+            rewind: RewindBuffer::default(),
+            rewinding: false,
+            movie: None,
+            test_bus: None,
+            fault: None,
+            last_fault: None,
+            logged_unmapped: HashSet::new(),
+            debugger: None,
+        };
+
+        emu.power_cycle();
+        Ok(emu)
     }
 }
@@ -0,0 +1,78 @@
+use sdl2::{
+    audio::{AudioQueue, AudioSpecDesired},
+    AudioSubsystem,
+};
+
+use super::Emulator;
+
+/// Output sample rate
+const SAMPLE_RATE: i32 = 44100;
+
+/// Maximum amount of queued audio before samples are dropped to keep the latency low
+const MAX_QUEUED_BYTES: u32 = SAMPLE_RATE as u32 * 4 / 10;
+
+pub struct AudioData {
+    /// Output queue, absent when running headless
+    queue: Option<AudioQueue<f32>>,
+    samples: Vec<f32>,
+    cycle_accumulator: f64,
+    sample_sum: f32,
+    sample_count: usize,
+}
+
+impl AudioData {
+    pub fn new() -> AudioData {
+        AudioData {
+            queue: None,
+            samples: Vec::new(),
+            cycle_accumulator: 0.0,
+            sample_sum: 0.0,
+            sample_count: 0,
+        }
+    }
+
+    pub fn open(&mut self, audio_subsystem: &AudioSubsystem) {
+        let spec = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let queue = audio_subsystem.open_queue(None, &spec).unwrap();
+        queue.resume();
+
+        self.queue = Some(queue);
+    }
+}
+
+impl Emulator {
+    /// Called on every CPU cycle, averages the output into samples
+    pub fn clock_audio(&mut self) {
+        let cycles_per_sample = self.region.cpu_clock_rate() / SAMPLE_RATE as f64;
+
+        self.audio.sample_sum += self.mapper.expansion_audio();
+        self.audio.sample_count += 1;
+        self.audio.cycle_accumulator += 1.0;
+
+        if self.audio.cycle_accumulator >= cycles_per_sample {
+            self.audio.cycle_accumulator -= cycles_per_sample;
+
+            let sample = self.audio.sample_sum / self.audio.sample_count as f32;
+            self.audio.samples.push(sample);
+
+            self.audio.sample_sum = 0.0;
+            self.audio.sample_count = 0;
+        }
+    }
+
+    /// Queues the samples generated during the frame
+    pub fn flush_audio(&mut self) {
+        if let Some(queue) = &self.audio.queue {
+            if queue.size() < MAX_QUEUED_BYTES {
+                queue.queue_audio(&self.audio.samples).unwrap();
+            }
+        }
+
+        self.audio.samples.clear();
+    }
+}
@@ -1,19 +1,72 @@
-use crate::inst::{AddressingMode, Instruction, Operand, INSTRUCTIONS};
+use crate::{
+    inst::{AddressingMode, Instruction, Operand, INSTRUCTIONS},
+    state::{StateError, StateReader, StateWriter},
+};
 
-use super::{Emulator, StatusRegister};
+use super::{EmulationFault, Emulator, StatusRegister};
+
+/// Addresses of the interrupt vectors
+/// https://www.nesdev.org/wiki/CPU_interrupts
+const NMI_VECTOR: u16 = 0xFFFA;
+const RESET_VECTOR: u16 = 0xFFFC;
+const IRQ_VECTOR: u16 = 0xFFFE;
+
+/// JSR fetches the high byte of its target after pushing the return address
+pub const JSR_OPCODE: u8 = 0x20;
+
+/// Bits of A that survive XAA and LXA, it depends on the chip and its temperature
+/// https://www.nesdev.org/wiki/Visual6502wiki/6502_Opcode_8B_(XAA,_ANE)
+const DEFAULT_UNSTABLE_MAGIC: u8 = 0xEE;
 
 pub struct CPUData {
-    cycle_advance: usize,
-    cycle_debt: usize,
+    /// CPU cycles since power on
+    pub cycles: u64,
+    /// Whether an interrupt was pending at the end of the last cycle
+    interrupt_poll: bool,
+    /// The poll of the cycle before, interrupts are detected on the penultimate cycle of an instruction
+    previous_interrupt_poll: bool,
+    /// Enters the interrupt handler instead of executing the next instruction
+    interrupt_pending: bool,
+    /// Set by the KIL opcodes, only a reset gets the CPU going again
+    jammed: bool,
+    /// Constant XAA and LXA OR the accumulator with
+    unstable_magic: u8,
+    /// Unofficial opcodes that were already logged, None when logging is disabled
+    logged_unofficial: Option<[bool; 256]>,
+    /// Trace lines of the executed instructions, None when tracing is disabled
+    pub trace: Option<Vec<String>>,
 }
 
 impl CPUData {
     pub fn new() -> CPUData {
         CPUData {
-            cycle_advance: 0,
-            cycle_debt: 0,
+            cycles: 0,
+            interrupt_poll: false,
+            previous_interrupt_poll: false,
+            interrupt_pending: false,
+            jammed: false,
+            unstable_magic: DEFAULT_UNSTABLE_MAGIC,
+            logged_unofficial: None,
+            trace: None,
         }
     }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u64(self.cycles);
+        writer.write_bool(self.interrupt_poll);
+        writer.write_bool(self.previous_interrupt_poll);
+        writer.write_bool(self.interrupt_pending);
+        writer.write_bool(self.jammed);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.cycles = reader.read_u64()?;
+        self.interrupt_poll = reader.read_bool()?;
+        self.previous_interrupt_poll = reader.read_bool()?;
+        self.interrupt_pending = reader.read_bool()?;
+        self.jammed = reader.read_bool()?;
+        Ok(())
+    }
 }
 
 impl Emulator {
@@ -46,6 +99,34 @@ impl Emulator {
         self.read(addr)
     }
 
+    /// The status byte pushed by PHP, BRK and interrupts, bit 5 is always set and bit 4 is
+    /// only set when software pushed it
+    pub fn pushed_flags(&self, brk: bool) -> u8 {
+        let mut flags = self.regs.flags.clone();
+        flags.set_break_command(brk.into());
+        flags.set_always_set(1);
+        flags.into_bytes()[0]
+    }
+
+    /// Loads a status byte pulled by PLP or RTI, bits 4 and 5 don't exist in the register
+    pub fn set_pulled_flags(&mut self, val: u8) {
+        self.regs.flags = StatusRegister::from_bytes([val])
+            .with_break_command(0)
+            .with_always_set(1);
+    }
+
+    /// The cycle spent incrementing the stack pointer reads the stack top without using it
+    pub fn dummy_stack_read(&mut self) {
+        self.read(0x100 + self.regs.sp as u16);
+    }
+
+    /// Reads the byte at PC and moves past it
+    fn fetch_byte(&mut self) -> u8 {
+        let val = self.read(self.regs.pc);
+        self.regs.pc = self.regs.pc.wrapping_add(1);
+        val
+    }
+
     fn get_zero_page_indirect_address(&mut self, off: u8) -> u16 {
         let low = self.read(off as u16);
         let high = self.read(off.wrapping_add(1) as u16);
@@ -59,78 +140,194 @@ impl Emulator {
         u16::from_le_bytes([low, high])
     }
 
+    /// Stores of SHA, SHX, SHY and TAS, the value is ANDed with the high byte of the base address
+    /// plus one and replaces the high byte of the address when the page is crossed
+    /// https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+    pub fn write_unstable(&mut self, op: Operand, val: u8) {
+        let (base, index) = match op {
+            Operand::AbsoluteIndexedX(addr) => (addr, self.regs.x),
+            Operand::AbsoluteIndexedY(addr) => (addr, self.regs.y),
+            Operand::ZeroPageIndirectIndexedY(off) => {
+                (self.get_zero_page_indirect_address(off), self.regs.y)
+            }
+            _ => unreachable!(),
+        };
+
+        let addr = self.index_address(base, index, true);
+        let val = val & ((base >> 8) as u8).wrapping_add(1);
+
+        let addr = if addr & 0xFF00 != base & 0xFF00 {
+            u16::from_le_bytes([addr as u8, val])
+        } else {
+            addr
+        };
+
+        self.write(addr, val);
+    }
+
+    /// Halts the CPU until the next reset, called after the KIL opcode is fetched
+    pub fn jam(&mut self) {
+        self.cpu.jammed = true;
+
+        let pc = self.regs.pc.wrapping_sub(1);
+        let opcode = self.peek(pc);
+        self.raise_fault(EmulationFault::CpuJammed { pc, opcode });
+    }
+
+    /// Forgets a jam and any pending interrupt so the next instruction starts from a clean CPU,
+    /// the registers are left alone
+    pub fn clear_cpu_halt(&mut self) {
+        self.cpu.jammed = false;
+        self.cpu.interrupt_poll = false;
+        self.cpu.previous_interrupt_poll = false;
+        self.cpu.interrupt_pending = false;
+    }
+
+    pub fn unstable_magic(&self) -> u8 {
+        self.cpu.unstable_magic
+    }
+
+    /// Sets the constant of XAA and LXA, usually 0x00, 0xEE or 0xFF
+    pub fn set_unstable_magic(&mut self, magic: u8) {
+        self.cpu.unstable_magic = magic;
+    }
+
+    /// Prints the first use of every unofficial opcode
+    pub fn enable_unofficial_log(&mut self) {
+        self.cpu.logged_unofficial = Some([false; 256]);
+    }
+
+    fn log_unofficial(&mut self, opcode: u8, ins: &Instruction, addr: u16) {
+        if let Some(logged) = &mut self.cpu.logged_unofficial {
+            if !std::mem::replace(&mut logged[opcode as usize], true) {
+                println!(
+                    "Unofficial opcode {:02X} ({}) used at {:04X}",
+                    opcode,
+                    ins.name.to_uppercase(),
+                    addr
+                );
+            }
+        }
+    }
+
     pub fn set_zero_and_negative_flags(&mut self, val: u8) {
         self.regs.flags.set_zero((val == 0).into());
         self.regs.flags.set_negative((val & (1 << 7) > 0).into());
     }
 
-    pub fn get_val_from_operand(&mut self, op: Operand) -> u8 {
-        if let Operand::Immediate(val) = op {
-            val
-        } else {
-            let addr = self.get_addr_from_operand(op);
-            self.read(addr)
+    /// Adds an index to an address, the CPU reads with only the low byte added first
+    /// and reads again if the high byte had to be fixed
+    /// https://www.nesdev.org/wiki/CPU_addressing_modes#Indexed_addressing
+    fn index_address(&mut self, base: u16, index: u8, always_dummy_read: bool) -> u16 {
+        let addr = base.wrapping_add(index as u16);
+        let unfixed = base & 0xFF00 | addr & 0x00FF;
+
+        if always_dummy_read || unfixed != addr {
+            self.read(unfixed);
         }
+
+        addr
     }
 
-    pub fn get_addr_from_operand(&mut self, op: Operand) -> u16 {
+    /// Computes the effective address with the dummy reads of the addressing mode,
+    /// writes and read-modify-writes can't skip the read before the high byte is fixed
+    fn resolve_address(&mut self, op: Operand, always_dummy_read: bool) -> u16 {
         match op {
             Operand::Absolute(addr) => addr,
-            Operand::AbsoluteIndexedX(addr) => addr.wrapping_add(self.regs.x as u16),
-            Operand::AbsoluteIndexedY(addr) => addr.wrapping_add(self.regs.y as u16),
+            Operand::AbsoluteIndexedX(addr) => {
+                self.index_address(addr, self.regs.x, always_dummy_read)
+            }
+            Operand::AbsoluteIndexedY(addr) => {
+                self.index_address(addr, self.regs.y, always_dummy_read)
+            }
             Operand::ZeroPage(off) => off as u16,
-            Operand::ZeroPageIndexedX(off) => off.wrapping_add(self.regs.x) as u16,
-            Operand::ZeroPageIndexedY(off) => off.wrapping_add(self.regs.y) as u16,
+            Operand::ZeroPageIndexedX(off) => {
+                self.read(off as u16);
+                off.wrapping_add(self.regs.x) as u16
+            }
+            Operand::ZeroPageIndexedY(off) => {
+                self.read(off as u16);
+                off.wrapping_add(self.regs.y) as u16
+            }
             Operand::ZeroPageIndexedXIndirect(off) => {
+                self.read(off as u16);
                 let zp_off = off.wrapping_add(self.regs.x);
                 self.get_zero_page_indirect_address(zp_off)
             }
             Operand::ZeroPageIndirectIndexedY(off) => {
                 let addr = self.get_zero_page_indirect_address(off);
-                addr.wrapping_add(self.regs.y as u16)
+                self.index_address(addr, self.regs.y, always_dummy_read)
             }
             _ => unreachable!(),
         }
     }
 
-    pub fn get_val_from_operand_cross(&mut self, op: Operand) -> (u8, bool) {
-        let mut page_crossed = false;
+    /// Reads the operand of an instruction that only reads memory
+    pub fn get_val_from_operand(&mut self, op: Operand) -> u8 {
+        if let Operand::Immediate(val) = op {
+            val
+        } else {
+            let addr = self.resolve_address(op, false);
+            self.read(addr)
+        }
+    }
 
-        let val = match op {
-            Operand::AbsoluteIndexedX(addr) => {
-                let final_addr = addr.wrapping_add(self.regs.x as u16);
+    /// Address the operand of a write or read-modify-write instruction points to
+    pub fn get_addr_from_operand(&mut self, op: Operand) -> u16 {
+        self.resolve_address(op, true)
+    }
 
-                page_crossed = final_addr & 0xFF00 != addr & 0xFF00;
-                self.read(final_addr)
-            }
-            Operand::AbsoluteIndexedY(addr) => {
-                let final_addr = addr.wrapping_add(self.regs.y as u16);
+    /// Reads a value, writes it back unmodified while the new value is computed, then writes the new value
+    pub fn read_modify_write(&mut self, addr: u16, modify: impl FnOnce(&mut Emulator, u8) -> u8) {
+        let val = self.read(addr);
+        self.write(addr, val);
 
-                page_crossed = final_addr & 0xFF00 != addr & 0xFF00;
-                self.read(final_addr)
-            }
-            Operand::ZeroPageIndirectIndexedY(off) => {
-                let addr = self.get_zero_page_indirect_address(off);
-                let final_addr = addr.wrapping_add(self.regs.y as u16);
+        let new_val = modify(self, val);
+        self.write(addr, new_val);
+    }
 
-                page_crossed = final_addr & 0xFF00 != addr & 0xFF00;
-                self.read(final_addr)
-            }
-            _ => self.get_val_from_operand(op),
+    /// Fetches the operand bytes after the opcode, one cycle each
+    fn fetch_operand(&mut self, addressing_mode: AddressingMode) -> Operand {
+        let mut fetch_address = || {
+            let low = self.fetch_byte();
+            let high = self.fetch_byte();
+            u16::from_le_bytes([low, high])
         };
 
-        (val, page_crossed)
+        match addressing_mode {
+            AddressingMode::Accumulator | AddressingMode::Implied => {
+                // the CPU always reads the byte after the opcode
+                self.read(self.regs.pc);
+
+                match addressing_mode {
+                    AddressingMode::Accumulator => Operand::Accumulator,
+                    _ => Operand::Implied,
+                }
+            }
+            AddressingMode::Absolute => Operand::Absolute(fetch_address()),
+            AddressingMode::AbsoluteIndirect => Operand::AbsoluteIndirect(fetch_address()),
+            AddressingMode::AbsoluteIndexedX => Operand::AbsoluteIndexedX(fetch_address()),
+            AddressingMode::AbsoluteIndexedY => Operand::AbsoluteIndexedY(fetch_address()),
+            AddressingMode::Immediate => Operand::Immediate(self.fetch_byte()),
+            AddressingMode::ZeroPage => Operand::ZeroPage(self.fetch_byte()),
+            AddressingMode::Relative => Operand::Relative(self.fetch_byte()),
+            AddressingMode::ZeroPageIndexedX => Operand::ZeroPageIndexedX(self.fetch_byte()),
+            AddressingMode::ZeroPageIndexedY => Operand::ZeroPageIndexedY(self.fetch_byte()),
+            AddressingMode::ZeroPageIndexedXIndirect => {
+                Operand::ZeroPageIndexedXIndirect(self.fetch_byte())
+            }
+            AddressingMode::ZeroPageIndirectIndexedY => {
+                Operand::ZeroPageIndirectIndexedY(self.fetch_byte())
+            }
+        }
     }
 
-    fn get_operand(&mut self, addresing_mode: AddressingMode) -> Operand {
-        let single_operand = self.read(self.regs.pc.wrapping_add(1));
-        let address_operand = {
-            let high = self.read(self.regs.pc.wrapping_add(1));
-            let low = self.read(self.regs.pc.wrapping_add(2));
-            u16::from_le_bytes([high, low])
-        };
+    /// Decodes the operand of the instruction at an address without side effects, for tracing
+    pub fn peek_operand(&mut self, addr: u16, addressing_mode: AddressingMode) -> Operand {
+        let single_operand = self.peek(addr.wrapping_add(1));
+        let address_operand = u16::from_le_bytes([single_operand, self.peek(addr.wrapping_add(2))]);
 
-        match addresing_mode {
+        match addressing_mode {
             AddressingMode::Accumulator => Operand::Accumulator,
             AddressingMode::Implied => Operand::Implied,
             AddressingMode::Immediate => Operand::Immediate(single_operand),
@@ -151,7 +348,8 @@ impl Emulator {
         }
     }
 
-    fn format_instruction(&self, inst: &Instruction, op: Operand) -> String {
+    /// Formats an instruction decoded at `addr` in assembly syntax
+    pub fn format_instruction(&self, addr: u16, inst: &Instruction, op: Operand) -> String {
         match op {
             Operand::Implied => inst.name.to_string(),
             Operand::Accumulator => format!("{} a", inst.name),
@@ -163,9 +361,7 @@ impl Emulator {
                 format!(
                     "{} ${:04X}",
                     inst.name,
-                    self.regs
-                        .pc
-                        .wrapping_add_signed(inst.bytes as i16)
+                    addr.wrapping_add_signed(inst.bytes as i16)
                         .wrapping_add_signed(operand as i8 as i16)
                 )
             }
@@ -184,95 +380,129 @@ impl Emulator {
         }
     }
 
-    pub fn clock_cpu(&mut self) {
-        let mut cycles_left = 1 + self.cpu.cycle_advance;
-        self.cpu.cycle_advance = 0;
+    /// Runs the rest of the system for the CPU cycle of a bus access
+    pub fn tick(&mut self) {
+        self.cpu.cycles += 1;
+
+        if self.test_bus.is_none() {
+            // PAL runs 3.2 PPU cycles per CPU cycle
+            let (ppu_cycles, cpu_cycles) = self.region.ppu_cpu_ratio();
+            self.cycle_counter += ppu_cycles;
+            while self.cycle_counter >= cpu_cycles {
+                self.clock_ppu();
+                self.cycle_counter -= cpu_cycles;
+            }
 
-        if self.cpu.cycle_debt > cycles_left {
-            self.cpu.cycle_debt -= cycles_left;
-            return;
-        } else {
-            cycles_left -= self.cpu.cycle_debt;
-            self.cpu.cycle_debt = 0;
+            self.mapper.clock_cpu();
+            self.clock_audio();
         }
 
-        while cycles_left > 0 {
-            let opcode = self.read(self.regs.pc);
-            let instruction = &INSTRUCTIONS[opcode as usize];
-
-            match instruction {
-                Some(ins) => {
-                    if ins.cycles > cycles_left {
-                        self.cpu.cycle_advance = cycles_left;
-                        return;
-                    }
-
-                    let operand = self.get_operand(ins.addressing_mode);
-
-                    // TODO: this slows down execution tremendously
-                    /*let ins_str = self.format_instruction(ins, operand);
-                    println!(
-                        "{:<04X}:\t{:<12}A: ${:<02X} X: ${:<02X} Y: ${:<02X} SP: ${:<02X} P: {:?}",
-                        self.regs.pc,
-                        ins_str,
-                        self.regs.a,
-                        self.regs.x,
-                        self.regs.y,
-                        self.regs.sp,
-                        self.regs.flags
-                    );*/
-
-                    self.regs.pc += ins.bytes as u16;
-                    let extra_cycles = (ins.callback)(self, operand);
-
-                    let total_cycles = ins.cycles + extra_cycles;
-                    if total_cycles > cycles_left {
-                        self.cpu.cycle_debt = total_cycles - cycles_left;
-                        cycles_left = 0;
-                    } else {
-                        cycles_left -= total_cycles;
-                    }
-                }
-                None => panic!("invalid opcode {}", opcode),
-            }
-        }
+        // https://www.nesdev.org/wiki/CPU_interrupts#Detailed_interrupt_behavior
+        let irq = self.mapper.irq() && self.regs.flags.interrupt_disable() == 0;
+        self.cpu.previous_interrupt_poll = self.cpu.interrupt_poll;
+        self.cpu.interrupt_poll = self.nmi_pending() || irq;
     }
 
-    pub fn nmi(&mut self) {
-        let (ret_high, ret_low) = {
-            let ret = self.regs.pc;
-            ((ret >> 8) as u8, (ret & 0xFF) as u8)
+    /// Executes the next instruction, or enters the interrupt handler if an interrupt was detected
+    /// during the last one. Returns the cycles it took
+    pub fn step_instruction(&mut self) -> usize {
+        let start_cycles = self.cpu.cycles;
+
+        if self.cpu.jammed {
+            // the CPU stops fetching, but the rest of the system keeps running
+            self.tick();
+            return 1;
+        }
+
+        if std::mem::take(&mut self.cpu.interrupt_pending) {
+            // the opcode and the next byte are read and thrown away
+            self.read(self.regs.pc);
+            self.read(self.regs.pc);
+            self.interrupt(false);
+
+            return (self.cpu.cycles - start_cycles) as usize;
+        }
+
+        self.debug_instruction_start();
+
+        if self.cpu.trace.is_some() {
+            self.trace_instruction();
+        }
+
+        let opcode = self.fetch_byte();
+        let ins = &INSTRUCTIONS[opcode as usize];
+
+        if ins.unofficial {
+            self.log_unofficial(opcode, ins, self.regs.pc.wrapping_sub(1));
+        }
+
+        let operand = if opcode == JSR_OPCODE {
+            Operand::Immediate(self.fetch_byte())
+        } else {
+            self.fetch_operand(ins.addressing_mode)
         };
 
+        (ins.callback)(self, operand);
+
+        self.cpu.interrupt_pending = self.cpu.previous_interrupt_poll;
+
+        (self.cpu.cycles - start_cycles) as usize
+    }
+
+    /// Pushes the return address and the flags and jumps through the IRQ vector,
+    /// or the NMI vector if an NMI arrived in the meantime
+    pub fn interrupt(&mut self, brk: bool) {
+        let [ret_low, ret_high] = self.regs.pc.to_le_bytes();
         self.push_on_stack(ret_high);
         self.push_on_stack(ret_low);
 
-        self.regs.flags.set_break_command(0);
-        self.regs.flags.set_interrupt_disable(1);
-
-        self.push_on_stack(self.regs.flags.bytes[0]);
+        self.push_on_stack(self.pushed_flags(brk));
 
-        let addr_low = self.read(0xFFFA) as u16;
-        let addr_high = self.read(0xFFFB) as u16;
+        self.regs.flags.set_interrupt_disable(1);
 
-        let addr = addr_high << 8 | addr_low;
+        let vector = if self.take_nmi() {
+            NMI_VECTOR
+        } else {
+            IRQ_VECTOR
+        };
 
-        self.regs.pc = addr;
+        let addr_low = self.read(vector);
+        let addr_high = self.read(vector + 1);
+        self.regs.pc = u16::from_le_bytes([addr_low, addr_high]);
     }
 
-    pub fn reset(&mut self) {
+    /// Clears the registers and runs the reset sequence, which leaves SP at 0xFD
+    pub fn power_on_cpu(&mut self) {
         self.regs.flags = StatusRegister::new().with_always_set(1);
 
         self.regs.a = 0;
         self.regs.x = 0;
         self.regs.y = 0;
+        self.regs.sp = 0;
+        self.cpu.cycles = 0;
+        self.cpu.interrupt_poll = false;
+        self.cpu.previous_interrupt_poll = false;
 
-        self.regs.sp = 0xFD;
+        self.reset_cpu();
+    }
 
-        let addr_low = self.read(0xFFFC) as u16;
-        let addr_high = self.read(0xFFFD) as u16;
-        let addr = addr_high << 8 | addr_low;
+    /// Runs the reset sequence, the registers keep their values except SP which goes down by 3
+    /// https://www.nesdev.org/wiki/CPU_power_up_state#At_reset
+    pub fn reset_cpu(&mut self) {
+        self.regs.flags.set_interrupt_disable(1);
+        self.cpu.interrupt_pending = false;
+        self.cpu.jammed = false;
+
+        // the reset sequence is an interrupt whose pushes are turned into reads
+        self.read(self.regs.pc);
+        self.read(self.regs.pc);
+        for _ in 0..3 {
+            self.dummy_stack_read();
+            self.regs.sp = self.regs.sp.wrapping_sub(1);
+        }
 
-        self.regs.pc = addr;
+        let addr_low = self.read(RESET_VECTOR);
+        let addr_high = self.read(RESET_VECTOR + 1);
+        self.regs.pc = u16::from_le_bytes([addr_low, addr_high]);
     }
 }
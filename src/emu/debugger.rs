@@ -0,0 +1,582 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use crate::inst::INSTRUCTIONS;
+
+use self::expr::{parse_number, Expr, ExprError};
+
+use super::{cpu::JSR_OPCODE, Emulator};
+
+mod expr;
+
+/// Number of instructions `dis` shows when no count is given
+const DEFAULT_DISASSEMBLY_LENGTH: usize = 10;
+
+/// Number of bytes `mem` shows when no length is given
+const DEFAULT_DUMP_LENGTH: usize = 64;
+
+const HELP: &str = "\
+continue, c                       run until the next break
+step, s [N]                       execute N instructions
+next, n                           step over subroutine calls
+finish, out                       run until the current subroutine returns
+scanline N                        run until the PPU enters scanline N
+break ADDR [if EXPR]              break before executing ADDR
+watch [r|w|x|rw|rwx] [ppu] START[-END] [if EXPR]
+                                  break after an access to an address range, writes by default
+delete N                          remove a breakpoint
+list                              show the breakpoints
+regs                              show the registers
+mem [ppu] ADDR [LEN]              dump memory
+dis [ADDR] [N]                    disassemble N instructions
+print EXPR                        evaluate an expression
+detach                            remove the debugger and keep running
+
+Addresses are hexadecimal. Expressions take decimal, $hex and 0xhex numbers, the registers
+a x y sp pc p, the PPU position scanline and dot, CPU memory as [ADDR] and the C operators
+! - + & | ^ == != < > <= >= && ||. An empty line repeats the last command.";
+
+/// Bus a breakpoint watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressSpace {
+    Cpu,
+
+    /// Accessed by the CPU through PPUDATA($2007)
+    Ppu,
+}
+
+impl AddressSpace {
+    /// Put before addresses to tell the buses apart
+    fn prefix(&self) -> &'static str {
+        match self {
+            AddressSpace::Cpu => "",
+            AddressSpace::Ppu => "PPU ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+
+    /// Opcode fetch of an instruction
+    Execute,
+}
+
+impl Access {
+    fn name(&self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Execute => "execute",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DebugError {
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    InvalidAddress(String),
+    InvalidWatchMode(String),
+    NoSuchBreakpoint(usize),
+    Expr(ExprError),
+}
+
+impl From<ExprError> for DebugError {
+    fn from(err: ExprError) -> Self {
+        DebugError::Expr(err)
+    }
+}
+
+impl fmt::Display for DebugError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebugError::UnknownCommand(command) => {
+                write!(f, "unknown command '{}', try 'help'", command)
+            }
+            DebugError::MissingArgument(argument) => write!(f, "missing {}", argument),
+            DebugError::InvalidAddress(addr) => write!(f, "invalid address '{}'", addr),
+            DebugError::InvalidWatchMode(mode) => write!(f, "invalid watch mode '{}'", mode),
+            DebugError::NoSuchBreakpoint(id) => write!(f, "no breakpoint {}", id),
+            DebugError::Expr(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Breakpoint or watchpoint on a range of addresses
+struct Breakpoint {
+    id: usize,
+    space: AddressSpace,
+    start: u16,
+    end: u16,
+    read: bool,
+    write: bool,
+    execute: bool,
+    /// Only breaks when the expression is non-zero
+    condition: Option<Expr>,
+    /// How the breakpoint was defined, for listing
+    description: String,
+}
+
+impl Breakpoint {
+    fn matches(&self, space: AddressSpace, addr: u16, access: Access) -> bool {
+        let enabled = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        };
+
+        enabled && self.space == space && (self.start..=self.end).contains(&addr)
+    }
+
+    fn condition_holds(&self, emu: &mut Emulator) -> bool {
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition.eval(emu) != 0)
+    }
+}
+
+/// What the CPU runs until before opening the console again
+enum StepMode {
+    Run,
+
+    /// Number of instructions left
+    Into(usize),
+
+    /// Returned from the subroutine call being stepped over
+    Over {
+        return_pc: u16,
+        sp: u8,
+    },
+
+    /// Returned from the current subroutine, the stack pointer went above its value
+    Out {
+        sp: u8,
+    },
+
+    /// The PPU entered the scanline
+    Scanline(usize),
+}
+
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    next_id: usize,
+    /// Breakpoints matched by the accesses of the current instruction, with the access
+    hits: Vec<(usize, String)>,
+    /// Address of the instruction being executed
+    instruction_pc: u16,
+    /// Breaks before the next instruction with the reason when set
+    pending_break: Option<String>,
+    step: StepMode,
+    previous_scanline: usize,
+    last_command: String,
+}
+
+impl Debugger {
+    fn new() -> Debugger {
+        Debugger {
+            breakpoints: Vec::new(),
+            next_id: 1,
+            hits: Vec::new(),
+            instruction_pc: 0,
+            pending_break: None,
+            step: StepMode::Run,
+            previous_scanline: 0,
+            last_command: String::new(),
+        }
+    }
+
+    /// Returns why the step finished, or None if it didn't
+    fn step_finished(&mut self, pc: u16, sp: u8, scanline: usize) -> Option<String> {
+        match self.step {
+            StepMode::Run => None,
+            StepMode::Into(left) if left > 1 => {
+                self.step = StepMode::Into(left - 1);
+                None
+            }
+            StepMode::Into(_) => Some("step".to_string()),
+            StepMode::Over {
+                return_pc,
+                sp: call_sp,
+            } => (pc == return_pc && sp == call_sp).then(|| "step over".to_string()),
+            StepMode::Out { sp: frame_sp } => {
+                (sp > frame_sp).then(|| "returned from subroutine".to_string())
+            }
+            StepMode::Scanline(target) => (scanline == target && self.previous_scanline != target)
+                .then(|| format!("scanline {}", target)),
+        }
+    }
+
+    /// Adds a breakpoint on an address range for the accesses in `mode`, a mix of r, w and x
+    fn add_breakpoint(
+        &mut self,
+        space: AddressSpace,
+        start: u16,
+        end: u16,
+        mode: &str,
+        condition: Option<&str>,
+    ) -> Result<(), DebugError> {
+        let (start, end) = (start.min(end), start.max(end));
+
+        let mut description = format!("{} {}${:04X}", mode, space.prefix(), start);
+        if end != start {
+            description += &format!("-${:04X}", end);
+        }
+        if let Some(condition) = condition {
+            description += &format!(" if {}", condition);
+        }
+
+        let breakpoint = Breakpoint {
+            id: self.next_id,
+            space,
+            start,
+            end,
+            read: mode.contains('r'),
+            write: mode.contains('w'),
+            execute: mode.contains('x'),
+            condition: condition.map(Expr::parse).transpose()?,
+            description,
+        };
+        self.next_id += 1;
+
+        println!("{}: {}", breakpoint.id, breakpoint.description);
+        self.breakpoints.push(breakpoint);
+        Ok(())
+    }
+}
+
+/// Parses a hexadecimal address with an optional `$` or `0x` prefix
+fn parse_address(text: &str) -> Result<u16, DebugError> {
+    let hex = text
+        .strip_prefix('$')
+        .or(text.strip_prefix("0x"))
+        .unwrap_or(text);
+
+    u16::from_str_radix(hex, 16).map_err(|_| DebugError::InvalidAddress(text.to_string()))
+}
+
+/// Splits the `if EXPR` off the end of a command
+fn split_condition(args: &str) -> (&str, Option<&str>) {
+    match args.split_once(" if ") {
+        Some((spec, condition)) => (spec, Some(condition.trim())),
+        None => (args, None),
+    }
+}
+
+impl Emulator {
+    /// Attaches the debugger, the console opens before the next instruction
+    pub fn enable_debugger(&mut self) {
+        let mut debugger = Debugger::new();
+        debugger.pending_break = Some("debugger attached".to_string());
+        self.debugger = Some(debugger);
+    }
+
+    /// Opens the console before the next instruction if the debugger is attached
+    pub fn debug_break(&mut self) {
+        if let Some(debugger) = &mut self.debugger {
+            debugger.pending_break = Some("break key".to_string());
+        }
+    }
+
+    /// Records the watchpoints an access matches, they break once the instruction completes
+    pub fn debug_access(&mut self, space: AddressSpace, addr: u16, access: Access, val: u8) {
+        let Some(debugger) = &mut self.debugger else {
+            return;
+        };
+
+        for breakpoint in &debugger.breakpoints {
+            if breakpoint.matches(space, addr, access) {
+                debugger.hits.push((
+                    breakpoint.id,
+                    format!(
+                        "{} {}${:04X} = ${:02X} by the instruction at ${:04X}",
+                        access.name(),
+                        space.prefix(),
+                        addr,
+                        val,
+                        debugger.instruction_pc
+                    ),
+                ));
+            }
+        }
+    }
+
+    /// Checks the breakpoints and the step before an instruction executes and opens the console
+    /// when one of them breaks
+    pub fn debug_instruction_start(&mut self) {
+        // taken out so the conditions can look at the rest of the emulator
+        let Some(mut debugger) = self.debugger.take() else {
+            return;
+        };
+
+        let pc = self.regs.pc;
+        let mut reasons: Vec<String> = debugger.pending_break.take().into_iter().collect();
+
+        for breakpoint in &debugger.breakpoints {
+            if breakpoint.matches(AddressSpace::Cpu, pc, Access::Execute)
+                && breakpoint.condition_holds(self)
+            {
+                reasons.push(format!("breakpoint {} at ${:04X}", breakpoint.id, pc));
+            }
+        }
+
+        for (id, access) in std::mem::take(&mut debugger.hits) {
+            let breakpoint = debugger.breakpoints.iter().find(|bp| bp.id == id);
+            if breakpoint.is_some_and(|breakpoint| breakpoint.condition_holds(self)) {
+                reasons.push(format!("watchpoint {}: {}", id, access));
+            }
+        }
+
+        let scanline = self.ppu_position().0;
+        reasons.extend(debugger.step_finished(pc, self.regs.sp, scanline));
+        debugger.previous_scanline = scanline;
+        debugger.instruction_pc = pc;
+
+        if !reasons.is_empty() {
+            debugger.step = StepMode::Run;
+        }
+        self.debugger = Some(debugger);
+
+        if !reasons.is_empty() {
+            for reason in reasons {
+                println!("Break: {}", reason);
+            }
+            self.debug_console();
+        }
+    }
+
+    /// Reads commands from stdin until one resumes the emulation, the emulator is frozen meanwhile
+    fn debug_console(&mut self) {
+        println!("{}", self.state_dump());
+        println!("{}", self.disassemble(self.regs.pc).0);
+
+        let stdin = io::stdin();
+        loop {
+            print!("(debug) ");
+            io::stdout().flush().ok();
+
+            let mut line = String::new();
+            if matches!(stdin.lock().read_line(&mut line), Ok(0) | Err(_)) {
+                println!("Debugger detached");
+                self.debugger = None;
+                return;
+            }
+
+            let mut line = line.trim().to_string();
+            if let Some(debugger) = &mut self.debugger {
+                if line.is_empty() {
+                    line = debugger.last_command.clone();
+                } else {
+                    debugger.last_command = line.clone();
+                }
+            }
+
+            match self.debug_command(&line) {
+                Ok(true) => return,
+                Ok(false) => {}
+                Err(err) => println!("{}", err),
+            }
+        }
+    }
+
+    /// Runs a console command, returns whether the emulation resumes
+    fn debug_command(&mut self, line: &str) -> Result<bool, DebugError> {
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+        let mut words = args.split_whitespace().peekable();
+
+        let step = match command {
+            "" => None,
+            "help" | "h" => {
+                println!("{}", HELP);
+                None
+            }
+            "continue" | "c" => Some(StepMode::Run),
+            "step" | "s" => {
+                let count = match words.next() {
+                    Some(count) => parse_number(count)?.max(1) as usize,
+                    None => 1,
+                };
+                Some(StepMode::Into(count))
+            }
+            "next" | "n" => {
+                if self.peek(self.regs.pc) == JSR_OPCODE {
+                    Some(StepMode::Over {
+                        return_pc: self.regs.pc.wrapping_add(3),
+                        sp: self.regs.sp,
+                    })
+                } else {
+                    Some(StepMode::Into(1))
+                }
+            }
+            "finish" | "out" => Some(StepMode::Out { sp: self.regs.sp }),
+            "scanline" => {
+                let scanline = words
+                    .next()
+                    .ok_or(DebugError::MissingArgument("scanline"))?;
+                Some(StepMode::Scanline(parse_number(scanline)? as usize))
+            }
+            "break" | "b" => {
+                let (spec, condition) = split_condition(args);
+                let addr = parse_address(spec.trim())?;
+
+                self.debugger_mut().add_breakpoint(
+                    AddressSpace::Cpu,
+                    addr,
+                    addr,
+                    "x",
+                    condition,
+                )?;
+                None
+            }
+            "watch" | "w" => {
+                let (spec, condition) = split_condition(args);
+                let mut spec = spec.split_whitespace().peekable();
+
+                let mode = spec
+                    .next_if(|mode| mode.chars().all(|ch| "rwx".contains(ch)))
+                    .unwrap_or("w");
+                let space = match spec.next_if_eq(&"ppu") {
+                    Some(_) => AddressSpace::Ppu,
+                    None => AddressSpace::Cpu,
+                };
+
+                let range = spec.next().ok_or(DebugError::MissingArgument("address"))?;
+                let (start, end) = match range.split_once('-') {
+                    Some((start, end)) => (parse_address(start)?, parse_address(end)?),
+                    None => (parse_address(range)?, parse_address(range)?),
+                };
+
+                // the CPU doesn't execute from the PPU bus
+                if mode.contains('x') && space == AddressSpace::Ppu {
+                    return Err(DebugError::InvalidWatchMode(mode.to_string()));
+                }
+
+                self.debugger_mut()
+                    .add_breakpoint(space, start, end, mode, condition)?;
+                None
+            }
+            "delete" | "d" => {
+                let id = words
+                    .next()
+                    .ok_or(DebugError::MissingArgument("breakpoint"))?;
+                let id = parse_number(id)? as usize;
+
+                let breakpoints = &mut self.debugger_mut().breakpoints;
+                let count = breakpoints.len();
+                breakpoints.retain(|breakpoint| breakpoint.id != id);
+                if breakpoints.len() == count {
+                    return Err(DebugError::NoSuchBreakpoint(id));
+                }
+                None
+            }
+            "list" | "l" => {
+                for breakpoint in &self.debugger_mut().breakpoints {
+                    println!("{}: {}", breakpoint.id, breakpoint.description);
+                }
+                None
+            }
+            "regs" | "r" => {
+                println!("{}", self.state_dump());
+                None
+            }
+            "mem" | "m" => {
+                let ppu = words.next_if_eq(&"ppu").is_some();
+                let addr = words.next().ok_or(DebugError::MissingArgument("address"))?;
+                let addr = parse_address(addr)?;
+                let len = match words.next() {
+                    Some(len) => parse_number(len)? as usize,
+                    None => DEFAULT_DUMP_LENGTH,
+                };
+
+                for line_start in (0..len).step_by(16) {
+                    let bytes: Vec<String> = (line_start..len.min(line_start + 16))
+                        .map(|off| {
+                            let addr = addr.wrapping_add(off as u16);
+                            let val = if ppu {
+                                self.ppu_peek(addr)
+                            } else {
+                                self.peek(addr)
+                            };
+                            format!("{:02X}", val)
+                        })
+                        .collect();
+                    println!(
+                        "{:04X}  {}",
+                        addr.wrapping_add(line_start as u16),
+                        bytes.join(" ")
+                    );
+                }
+                None
+            }
+            "dis" => {
+                let mut addr = match words.next() {
+                    Some(addr) => parse_address(addr)?,
+                    None => self.regs.pc,
+                };
+                let count = match words.next() {
+                    Some(count) => parse_number(count)? as usize,
+                    None => DEFAULT_DISASSEMBLY_LENGTH,
+                };
+
+                for _ in 0..count {
+                    let (line, next) = self.disassemble(addr);
+                    println!("{}", line);
+                    addr = next;
+                }
+                None
+            }
+            "print" | "p" => {
+                let val = Expr::parse(args)?.eval(self);
+                println!("= {} (${:X})", val, val);
+                None
+            }
+            "detach" => {
+                println!("Debugger detached");
+                self.debugger = None;
+                return Ok(true);
+            }
+            _ => return Err(DebugError::UnknownCommand(command.to_string())),
+        };
+
+        match step {
+            Some(step) => {
+                self.debugger_mut().step = step;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// The debugger while the console is open
+    fn debugger_mut(&mut self) -> &mut Debugger {
+        self.debugger
+            .as_mut()
+            .expect("the console only runs with the debugger attached")
+    }
+
+    /// Formats the instruction at an address, returns the line and the address of the next one
+    fn disassemble(&mut self, addr: u16) -> (String, u16) {
+        let opcode = self.peek(addr);
+        let ins = &INSTRUCTIONS[opcode as usize];
+        let operand = self.peek_operand(addr, ins.addressing_mode);
+
+        let bytes: Vec<String> = (0..ins.bytes as u16)
+            .map(|off| format!("{:02X}", self.peek(addr.wrapping_add(off))))
+            .collect();
+
+        let line = format!(
+            "{:04X}  {:<8} {}{}",
+            addr,
+            bytes.join(" "),
+            if ins.unofficial { '*' } else { ' ' },
+            self.format_instruction(addr, ins, operand)
+        );
+
+        (line, addr.wrapping_add(ins.bytes as u16))
+    }
+}
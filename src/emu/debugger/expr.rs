@@ -0,0 +1,344 @@
+use std::fmt;
+
+use crate::emu::Emulator;
+
+#[derive(Debug)]
+pub enum ExprError {
+    /// The expression ended where an operand or a closing bracket was expected
+    UnexpectedEnd,
+
+    /// A token that doesn't fit where it is
+    UnexpectedToken(String),
+
+    /// A number that couldn't be parsed
+    InvalidNumber(String),
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ExprError::UnexpectedToken(token) => write!(f, "unexpected '{}'", token),
+            ExprError::InvalidNumber(number) => write!(f, "invalid number '{}'", number),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    Pc,
+    P,
+    Scanline,
+    Dot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Sub,
+}
+
+impl BinaryOp {
+    /// Operators grouped by precedence, lowest first
+    const LEVELS: [&'static [(&'static str, BinaryOp)]; 6] = [
+        &[("||", BinaryOp::Or)],
+        &[("&&", BinaryOp::And)],
+        &[
+            ("==", BinaryOp::Equal),
+            ("!=", BinaryOp::NotEqual),
+            ("<=", BinaryOp::LessEqual),
+            (">=", BinaryOp::GreaterEqual),
+            ("<", BinaryOp::Less),
+            (">", BinaryOp::Greater),
+        ],
+        &[("|", BinaryOp::BitOr), ("^", BinaryOp::BitXor)],
+        &[("&", BinaryOp::BitAnd)],
+        &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    ];
+
+    fn apply(&self, lhs: i64, rhs: i64) -> i64 {
+        match self {
+            BinaryOp::Or => (lhs != 0 || rhs != 0).into(),
+            BinaryOp::And => (lhs != 0 && rhs != 0).into(),
+            BinaryOp::Equal => (lhs == rhs).into(),
+            BinaryOp::NotEqual => (lhs != rhs).into(),
+            BinaryOp::Less => (lhs < rhs).into(),
+            BinaryOp::LessEqual => (lhs <= rhs).into(),
+            BinaryOp::Greater => (lhs > rhs).into(),
+            BinaryOp::GreaterEqual => (lhs >= rhs).into(),
+            BinaryOp::BitOr => lhs | rhs,
+            BinaryOp::BitXor => lhs ^ rhs,
+            BinaryOp::BitAnd => lhs & rhs,
+            BinaryOp::Add => lhs.wrapping_add(rhs),
+            BinaryOp::Sub => lhs.wrapping_sub(rhs),
+        }
+    }
+}
+
+/// Condition of a breakpoint over the registers, the PPU position and CPU memory
+/// a == $10 && [$0300] != 0 || scanline >= 240
+#[derive(Debug)]
+pub enum Expr {
+    Number(i64),
+    Register(Register),
+    /// Byte of the CPU address space at an address
+    Memory(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Expr, ExprError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0 };
+
+        let expr = parser.binary(0)?;
+        match parser.tokens.get(parser.pos) {
+            Some(token) => Err(ExprError::UnexpectedToken(token.clone())),
+            None => Ok(expr),
+        }
+    }
+
+    /// Evaluates the expression without side effects on the emulated system
+    pub fn eval(&self, emu: &mut Emulator) -> i64 {
+        match self {
+            Expr::Number(val) => *val,
+            Expr::Register(reg) => match reg {
+                Register::A => emu.regs.a as i64,
+                Register::X => emu.regs.x as i64,
+                Register::Y => emu.regs.y as i64,
+                Register::Sp => emu.regs.sp as i64,
+                Register::Pc => emu.regs.pc as i64,
+                Register::P => emu.regs.flags.bytes[0] as i64,
+                Register::Scanline => emu.ppu_position().0 as i64,
+                Register::Dot => emu.ppu_position().1 as i64,
+            },
+            Expr::Memory(addr) => {
+                let addr = addr.eval(emu) as u16;
+                emu.peek(addr) as i64
+            }
+            Expr::Not(expr) => (expr.eval(emu) == 0).into(),
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(emu);
+                op.apply(lhs, rhs.eval(emu))
+            }
+        }
+    }
+}
+
+/// Parses `$C000` and `0xC000` as hexadecimal and anything else as decimal
+pub fn parse_number(text: &str) -> Result<i64, ExprError> {
+    let parsed = if let Some(hex) = text.strip_prefix('$').or(text.strip_prefix("0x")) {
+        i64::from_str_radix(hex, 16)
+    } else {
+        text.parse()
+    };
+
+    parsed.map_err(|_| ExprError::InvalidNumber(text.to_string()))
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&ch) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+        } else if ch.is_ascii_alphanumeric() || ch == '$' || ch == '_' {
+            let mut word = String::new();
+            while let Some(&ch) = chars.peek() {
+                if !(ch.is_ascii_alphanumeric() || ch == '$' || ch == '_') {
+                    break;
+                }
+                word.push(ch);
+                chars.next();
+            }
+            tokens.push(word);
+        } else {
+            chars.next();
+
+            // two character operators
+            let pair = chars.peek().map(|&next| format!("{}{}", ch, next));
+            if let Some(pair) =
+                pair.filter(|pair| matches!(pair.as_str(), "||" | "&&" | "==" | "!=" | "<=" | ">="))
+            {
+                chars.next();
+                tokens.push(pair);
+            } else if "()[]!<>|^&+-".contains(ch) {
+                tokens.push(ch.to_string());
+            } else {
+                return Err(ExprError::UnexpectedToken(ch.to_string()));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Result<String, ExprError> {
+        let token = self.tokens.get(self.pos).ok_or(ExprError::UnexpectedEnd)?;
+        self.pos += 1;
+        Ok(token.clone())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), ExprError> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(ExprError::UnexpectedToken(token));
+        }
+        Ok(())
+    }
+
+    /// Parses operators of the given precedence level and higher, left associative
+    fn binary(&mut self, level: usize) -> Result<Expr, ExprError> {
+        let Some(ops) = BinaryOp::LEVELS.get(level) else {
+            return self.unary();
+        };
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(&(_, op)) = self
+            .tokens
+            .get(self.pos)
+            .and_then(|token| ops.iter().find(|(name, _)| name == token))
+        {
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        let token = self.next()?;
+
+        match token.as_str() {
+            "!" => Ok(Expr::Not(Box::new(self.unary()?))),
+            "-" => Ok(Expr::Binary(
+                BinaryOp::Sub,
+                Box::new(Expr::Number(0)),
+                Box::new(self.unary()?),
+            )),
+            "(" => {
+                let expr = self.binary(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            "[" => {
+                let addr = self.binary(0)?;
+                self.expect("]")?;
+                Ok(Expr::Memory(Box::new(addr)))
+            }
+            _ => {
+                let register = match token.to_ascii_lowercase().as_str() {
+                    "a" => Some(Register::A),
+                    "x" => Some(Register::X),
+                    "y" => Some(Register::Y),
+                    "sp" | "s" => Some(Register::Sp),
+                    "pc" => Some(Register::Pc),
+                    "p" => Some(Register::P),
+                    "scanline" => Some(Register::Scanline),
+                    "dot" => Some(Register::Dot),
+                    _ => None,
+                };
+
+                match register {
+                    Some(register) => Ok(Expr::Register(register)),
+                    None => Ok(Expr::Number(parse_number(&token)?)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emu::Emulator;
+
+    use super::{parse_number, Expr, ExprError};
+
+    fn eval(text: &str, emu: &mut Emulator) -> i64 {
+        Expr::parse(text).unwrap().eval(emu)
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("$C000").unwrap(), 0xC000);
+        assert_eq!(parse_number("0x10").unwrap(), 0x10);
+        assert_eq!(parse_number("42").unwrap(), 42);
+        assert!(matches!(
+            parse_number("$G0"),
+            Err(ExprError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        let mut emu = Emulator::with_test_bus();
+
+        assert_eq!(eval("1 + 2 == 3 && 0 || 1", &mut emu), 1);
+        assert_eq!(eval("10 - 3 - 2", &mut emu), 5);
+        assert_eq!(eval("$F0 | $0F & $03", &mut emu), 0xF3);
+        assert_eq!(eval("($F0 | $0F) & $03", &mut emu), 0x03);
+        assert_eq!(eval("!0 + -1", &mut emu), 0);
+        assert_eq!(eval("2 <= 2 && 3 > 2 && 1 != 2", &mut emu), 1);
+    }
+
+    #[test]
+    fn registers_and_memory() {
+        let mut emu = Emulator::with_test_bus();
+        emu.regs.a = 0x10;
+        emu.regs.x = 2;
+        emu.poke_test_bus(0x0302, 0x55);
+
+        assert_eq!(eval("A == $10", &mut emu), 1);
+        assert_eq!(eval("[$0300 + x]", &mut emu), 0x55);
+        assert_eq!(eval("[$0300 + x] != 0 && a == 16", &mut emu), 1);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(matches!(Expr::parse("a =="), Err(ExprError::UnexpectedEnd)));
+        assert!(matches!(
+            Expr::parse("[$0300"),
+            Err(ExprError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            Expr::parse("a @ 1"),
+            Err(ExprError::UnexpectedToken(_))
+        ));
+        assert!(matches!(
+            Expr::parse("a 1"),
+            Err(ExprError::UnexpectedToken(_))
+        ));
+        assert!(matches!(
+            Expr::parse("(a ]"),
+            Err(ExprError::UnexpectedToken(_))
+        ));
+        assert!(matches!(
+            Expr::parse("foo"),
+            Err(ExprError::InvalidNumber(_))
+        ));
+    }
+}
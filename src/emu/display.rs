@@ -0,0 +1,94 @@
+use sdl2::{pixels::PixelFormatEnum, render::Canvas, video::Window, EventPump};
+
+use crate::{
+    crt::CrtFilter,
+    ntsc::{NtscFilter, NTSC_OUTPUT_WIDTH},
+};
+
+use super::{Emulator, ORIGINAL_HEIGHT, ORIGINAL_WIDTH};
+
+/// SDL window the frames are presented in, absent when running headless
+pub struct Display {
+    pub canvas: Canvas<Window>,
+    pub event_pump: EventPump,
+}
+
+/// Picks the last stage of the output pipeline: the frame buffer, the NTSC filter, then the CRT
+fn select_output<'a>(
+    frame_buffer: &'a [u8],
+    ntsc_filter: &'a Option<NtscFilter>,
+    crt: Option<&'a CrtFilter>,
+) -> (u32, u32, &'a [u8]) {
+    match (crt, ntsc_filter) {
+        (Some(crt), _) => crt.output(),
+        (None, Some(filter)) => (NTSC_OUTPUT_WIDTH as u32, ORIGINAL_HEIGHT, filter.output()),
+        (None, None) => (ORIGINAL_WIDTH, ORIGINAL_HEIGHT, frame_buffer),
+    }
+}
+
+impl Emulator {
+    /// Initializes SDL and opens the window and the audio output
+    pub fn open_window(&mut self) {
+        let sdl_context = sdl2::init().unwrap();
+        let video_subsystem = sdl_context.video().unwrap();
+        let audio_subsystem = sdl_context.audio().unwrap();
+
+        let window = video_subsystem
+            .window(
+                "baroness",
+                ORIGINAL_WIDTH * self.window_scale,
+                ORIGINAL_HEIGHT * self.window_scale,
+            )
+            .position_centered()
+            .build()
+            .unwrap();
+
+        let mut canvas = window.into_canvas().build().unwrap();
+
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(255, 0, 0));
+        canvas.clear();
+        canvas.present();
+
+        let event_pump = sdl_context.event_pump().unwrap();
+
+        self.audio.open(&audio_subsystem);
+        self.display = Some(Display { canvas, event_pump });
+    }
+
+    /// Runs the output filters and shows the result in the window, called at the start of VBlank
+    pub fn present_frame(&mut self) {
+        if let Some(filter) = &mut self.ntsc_filter {
+            filter.filter(&self.ppu.index_buffer);
+        }
+
+        if let Some(crt) = &mut self.crt {
+            let (width, _, pixels) = select_output(&self.ppu.frame_buffer, &self.ntsc_filter, None);
+            crt.process(width, pixels);
+        }
+
+        let Some(display) = &mut self.display else {
+            return;
+        };
+
+        let (width, height, pixels) =
+            select_output(&self.ppu.frame_buffer, &self.ntsc_filter, self.crt.as_ref());
+
+        let texture_creator = display.canvas.texture_creator();
+        let mut texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, width, height)
+            .unwrap();
+        texture.update(None, pixels, width as usize * 3).unwrap();
+        display.canvas.copy(&texture, None, None).unwrap();
+
+        self.draw_overlay();
+
+        if let Some(display) = &mut self.display {
+            display.canvas.present();
+        }
+    }
+
+    /// Width, height and RGB24 pixels of the image shown, after the enabled filters
+    pub fn output_frame(&self) -> (u32, u32, &[u8]) {
+        select_output(&self.ppu.frame_buffer, &self.ntsc_filter, self.crt.as_ref())
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt;
+
+use crate::mapper::MapperError;
+
+use super::{debugger::AddressSpace, Emulator};
+
+/// Something the emulated system did that real hardware would survive but the game most likely
+/// doesn't, reported at the end of the frame instead of aborting. Unmapped accesses are harmless
+/// open bus accesses on hardware and are only logged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmulationFault {
+    /// A KIL opcode halted the CPU, it stays halted until a reset
+    CpuJammed { pc: u16, opcode: u8 },
+
+    /// Nothing responded to a read, the open bus value was returned
+    UnmappedRead(u16),
+
+    /// Nothing responded to a write, it was ignored
+    UnmappedWrite { addr: u16, val: u8 },
+
+    /// The cartridge didn't respond to a PPU read, the open bus value was returned
+    UnmappedPpuRead(u16),
+
+    /// The cartridge didn't respond to a PPU write, it was ignored
+    UnmappedPpuWrite { addr: u16, val: u8 },
+
+    /// The cartridge uses hardware the mapper doesn't emulate
+    UnsupportedMapperFeature { addr: u16, feature: &'static str },
+}
+
+impl EmulationFault {
+    /// Turns a failed cartridge access on either bus into a fault
+    pub fn from_mapper_error(
+        err: MapperError,
+        space: AddressSpace,
+        addr: u16,
+        write: Option<u8>,
+    ) -> EmulationFault {
+        match (err, space, write) {
+            (MapperError::Unmapped, AddressSpace::Cpu, Some(val)) => {
+                EmulationFault::UnmappedWrite { addr, val }
+            }
+            (MapperError::Unmapped, AddressSpace::Cpu, None) => EmulationFault::UnmappedRead(addr),
+            (MapperError::Unmapped, AddressSpace::Ppu, Some(val)) => {
+                EmulationFault::UnmappedPpuWrite { addr, val }
+            }
+            (MapperError::Unmapped, AddressSpace::Ppu, None) => {
+                EmulationFault::UnmappedPpuRead(addr)
+            }
+            (MapperError::Unsupported(feature), _, _) => {
+                EmulationFault::UnsupportedMapperFeature { addr, feature }
+            }
+        }
+    }
+}
+
+impl fmt::Display for EmulationFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmulationFault::CpuJammed { pc, opcode } => {
+                write!(f, "CPU jammed by opcode {:02X} at {:04X}", opcode, pc)
+            }
+            EmulationFault::UnmappedRead(addr) => write!(f, "read from unmapped {:04X}", addr),
+            EmulationFault::UnmappedWrite { addr, val } => {
+                write!(f, "write of {:02X} to unmapped {:04X}", val, addr)
+            }
+            EmulationFault::UnmappedPpuRead(addr) => {
+                write!(f, "read from unmapped PPU {:04X}", addr)
+            }
+            EmulationFault::UnmappedPpuWrite { addr, val } => {
+                write!(f, "write of {:02X} to unmapped PPU {:04X}", val, addr)
+            }
+            EmulationFault::UnsupportedMapperFeature { addr, feature } => {
+                write!(f, "unsupported mapper feature at {:04X}: {}", addr, feature)
+            }
+        }
+    }
+}
+
+impl Emulator {
+    /// Records a fault, only the first one of a frame is reported. Unmapped accesses are logged
+    /// the first time an address is accessed and don't interrupt the game.
+    pub fn raise_fault(&mut self, fault: EmulationFault) {
+        let key = match fault {
+            EmulationFault::UnmappedRead(addr) => (AddressSpace::Cpu, addr, false),
+            EmulationFault::UnmappedWrite { addr, .. } => (AddressSpace::Cpu, addr, true),
+            EmulationFault::UnmappedPpuRead(addr) => (AddressSpace::Ppu, addr, false),
+            EmulationFault::UnmappedPpuWrite { addr, .. } => (AddressSpace::Ppu, addr, true),
+            _ => {
+                self.fault.get_or_insert(fault);
+                return;
+            }
+        };
+
+        if self.logged_unmapped.insert(key) {
+            eprintln!("Open bus access: {}", fault);
+        }
+    }
+
+    /// Registers and timing at the moment of the call
+    pub fn state_dump(&self) -> String {
+        let (scanline, dot) = self.ppu_position();
+
+        format!(
+            "PC:{:04X} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            self.regs.pc,
+            self.regs.a,
+            self.regs.x,
+            self.regs.y,
+            self.regs.flags.bytes[0],
+            self.regs.sp,
+            scanline,
+            dot,
+            self.cpu.cycles
+        )
+    }
+
+    /// Prints a fault with the state and shows it over the frame, repeats of the last fault are ignored
+    pub fn report_fault(&mut self, fault: EmulationFault) {
+        if self.last_fault.as_ref() == Some(&fault) {
+            return;
+        }
+
+        eprintln!("Emulation fault: {}", fault);
+        eprintln!("State at the end of the frame: {}", self.state_dump());
+
+        self.overlay = vec![
+            "EMULATION FAULT".to_string(),
+            fault.to_string(),
+            String::new(),
+            "F3: RESET  F5: POWER CYCLE".to_string(),
+        ];
+        self.last_fault = Some(fault);
+    }
+
+    /// Removes the last fault from the screen once the console is reset
+    pub fn dismiss_fault(&mut self) {
+        if self.last_fault.take().is_some() {
+            self.overlay.clear();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use sdl2::keyboard::Scancode;
+
+use crate::state::{StateError, StateReader, StateWriter};
+
+use super::Emulator;
+
+/// Button bits in the order the controller shifts them out
+pub const BUTTON_A: u8 = 1 << 0;
+pub const BUTTON_B: u8 = 1 << 1;
+pub const BUTTON_SELECT: u8 = 1 << 2;
+pub const BUTTON_START: u8 = 1 << 3;
+pub const BUTTON_UP: u8 = 1 << 4;
+pub const BUTTON_DOWN: u8 = 1 << 5;
+pub const BUTTON_LEFT: u8 = 1 << 6;
+pub const BUTTON_RIGHT: u8 = 1 << 7;
+
+/// Keyboard layout of the first controller
+const KEYMAP: [(Scancode, u8); 8] = [
+    (Scancode::X, BUTTON_A),
+    (Scancode::Z, BUTTON_B),
+    (Scancode::RShift, BUTTON_SELECT),
+    (Scancode::Return, BUTTON_START),
+    (Scancode::Up, BUTTON_UP),
+    (Scancode::Down, BUTTON_DOWN),
+    (Scancode::Left, BUTTON_LEFT),
+    (Scancode::Right, BUTTON_RIGHT),
+];
+
+/// Upper bits of $4016/$4017 reads come from the open bus, which usually holds the high byte of the address
+const OPEN_BUS: u8 = 0x40;
+
+/// Standard controller
+/// https://www.nesdev.org/wiki/Standard_controller
+#[derive(Default)]
+struct Controller {
+    buttons: u8,
+    shift: u8,
+}
+
+#[derive(Default)]
+pub struct InputData {
+    strobe: bool,
+    controllers: [Controller; 2],
+    /// COMMAND_* bits requested from the keyboard, executed before the next frame
+    queued_commands: u8,
+}
+
+impl InputData {
+    pub fn new() -> InputData {
+        InputData::default()
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.strobe);
+        for controller in &self.controllers {
+            writer.write_u8(controller.buttons);
+            writer.write_u8(controller.shift);
+        }
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.strobe = reader.read_bool()?;
+        for controller in &mut self.controllers {
+            controller.buttons = reader.read_u8()?;
+            controller.shift = reader.read_u8()?;
+        }
+        Ok(())
+    }
+}
+
+impl Emulator {
+    /// Runs a movie command like a reset before the next frame, and records it if a movie is being recorded
+    pub fn queue_command(&mut self, command: u8) {
+        self.input.queued_commands |= command;
+    }
+
+    /// Takes the commands queued since the last frame
+    pub fn take_queued_commands(&mut self) -> u8 {
+        std::mem::take(&mut self.input.queued_commands)
+    }
+
+    /// Buttons of the first controller currently held on the keyboard
+    pub fn keyboard_buttons(&self) -> u8 {
+        let Some(display) = &self.display else {
+            return 0;
+        };
+        let keyboard = display.event_pump.keyboard_state();
+
+        KEYMAP
+            .iter()
+            .filter(|(scancode, _)| keyboard.is_scancode_pressed(*scancode))
+            .fold(0, |buttons, (_, button)| buttons | button)
+    }
+
+    /// Sets the buttons held on both controllers for the next frame
+    pub fn set_buttons(&mut self, buttons: [u8; 2]) {
+        for (controller, buttons) in self.input.controllers.iter_mut().zip(buttons) {
+            controller.buttons = buttons;
+            if self.input.strobe {
+                controller.shift = buttons;
+            }
+        }
+    }
+
+    /// $4016 and $4017 reads
+    pub fn read_controller(&mut self, port: usize) -> u8 {
+        let controller = &mut self.input.controllers[port];
+        if self.input.strobe {
+            controller.shift = controller.buttons;
+        }
+
+        let bit = controller.shift & 1;
+        // Official controllers return 1 after all 8 buttons were read
+        controller.shift = controller.shift >> 1 | 0x80;
+
+        OPEN_BUS | bit
+    }
+
+    /// $4016 writes, reloads the shift registers while the strobe is high
+    pub fn write_controller_strobe(&mut self, val: u8) {
+        self.input.strobe = val & 1 > 0;
+        if self.input.strobe {
+            for controller in &mut self.input.controllers {
+                controller.shift = controller.buttons;
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use crate::movie::{
+    Movie, MovieError, MovieFrame, MovieStart, COMMAND_FDS_SELECT, COMMAND_POWER,
+    COMMAND_SOFT_RESET,
+};
+
+use super::Emulator;
+
+enum MovieMode {
+    /// Frames are appended and written to the path once the emulator exits
+    Recording(PathBuf),
+    /// Index of the next frame to play
+    Playing(usize),
+}
+
+pub struct MovieSession {
+    movie: Movie,
+    mode: MovieMode,
+}
+
+impl Emulator {
+    /// Starts recording from the current state of the machine, from power on if it hasn't run yet,
+    /// `rom_name` is stored in FM2 movies
+    pub fn record_movie(&mut self, path: PathBuf, rom_name: String, from_state: bool) {
+        let start = if from_state {
+            MovieStart::SaveState(self.snapshot())
+        } else {
+            MovieStart::PowerOn
+        };
+
+        self.movie = Some(MovieSession {
+            movie: Movie::new(start, self.rom_md5, rom_name, self.region),
+            mode: MovieMode::Recording(path),
+        });
+    }
+
+    /// Starts playing the movie, restoring its save state first if it has one
+    pub fn play_movie(&mut self, movie: Movie) -> Result<(), MovieError> {
+        if movie.rom_md5.is_some_and(|md5| md5 != self.rom_md5) {
+            eprintln!("Warning: the movie was recorded with a different ROM");
+        }
+
+        if movie.region != self.region {
+            eprintln!("Switching to {} timing for the movie", movie.region.name());
+            self.region = movie.region;
+        }
+
+        if let MovieStart::SaveState(state) = &movie.start {
+            self.restore(state).map_err(MovieError::StartState)?;
+        }
+
+        self.movie = Some(MovieSession {
+            movie,
+            mode: MovieMode::Playing(0),
+        });
+
+        Ok(())
+    }
+
+    /// Writes the recording, called when the emulator exits
+    pub fn finish_movie(&mut self) -> Result<(), MovieError> {
+        let Some(MovieSession {
+            movie,
+            mode: MovieMode::Recording(path),
+        }) = self.movie.take()
+        else {
+            return Ok(());
+        };
+
+        movie.save(&path)?;
+        eprintln!(
+            "Recorded {} frames to {}",
+            movie.frames.len(),
+            path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Called before every frame, feeds the controllers from the keyboard or the movie
+    pub fn update_input(&mut self) {
+        let mut frame = MovieFrame {
+            command: self.take_queued_commands(),
+            buttons: [self.keyboard_buttons(), 0],
+        };
+
+        match &mut self.movie {
+            Some(MovieSession {
+                movie,
+                mode: MovieMode::Recording(_),
+            }) => movie.frames.push(frame),
+            Some(MovieSession {
+                movie,
+                mode: MovieMode::Playing(next),
+            }) => match movie.frames.get(*next) {
+                Some(&movie_frame) => {
+                    frame = movie_frame;
+                    *next += 1;
+                }
+                None => {
+                    eprintln!("Movie finished after {} frames", movie.frames.len());
+                    self.movie = None;
+                }
+            },
+            None => {}
+        }
+
+        if frame.command & COMMAND_POWER > 0 {
+            self.power_cycle();
+        } else if frame.command & COMMAND_SOFT_RESET > 0 {
+            self.soft_reset();
+        }
+        if frame.command & COMMAND_FDS_SELECT > 0 {
+            self.mapper.switch_disk_side();
+        }
+
+        self.set_buttons(frame.buttons);
+    }
+}
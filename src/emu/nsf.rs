@@ -0,0 +1,181 @@
+use crate::{
+    nsf::{NSFInfo, TRACK_REG},
+    state::{StateError, StateReader, StateWriter},
+};
+
+use super::Emulator;
+
+/// Number of tracks shown in the track list
+const VISIBLE_TRACKS: usize = 16;
+
+pub struct NSFPlayer {
+    info: NSFInfo,
+    track: u8,
+    frames: usize,
+}
+
+impl NSFPlayer {
+    pub fn new(info: NSFInfo) -> NSFPlayer {
+        NSFPlayer {
+            track: info.start_track,
+            info,
+            frames: 0,
+        }
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.track);
+        writer.write_usize(self.frames);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        let track = reader.read_u8()?;
+        if track >= self.track_count() {
+            return Err(StateError::Corrupted);
+        }
+
+        self.track = track;
+        self.frames = reader.read_usize()?;
+        Ok(())
+    }
+
+    fn track_count(&self) -> u8 {
+        self.info.tracks.len() as u8
+    }
+
+    /// Length of the track including the fade out in milliseconds
+    fn track_length(&self, track: u8) -> Option<u32> {
+        let track = &self.info.tracks[track as usize];
+        track.length.map(|length| length + track.fade.unwrap_or(0))
+    }
+
+    fn elapsed_ms(&self, frame_rate: f64) -> u32 {
+        (self.frames as f64 * 1000.0 / frame_rate) as u32
+    }
+}
+
+fn format_time(ms: u32) -> String {
+    let seconds = ms / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+impl Emulator {
+    /// Restarts the CPU with the driver calling INIT for the track
+    pub fn nsf_select_track(&mut self, track: u8) {
+        let Some(player) = &mut self.nsf else {
+            return;
+        };
+
+        player.track = track;
+        player.frames = 0;
+
+        self.mapper.write_cpu(TRACK_REG, track).unwrap();
+        self.soft_reset();
+        self.update_nsf_overlay();
+    }
+
+    /// Starts playing the first track
+    pub fn nsf_start(&mut self) {
+        if let Some(player) = &self.nsf {
+            self.nsf_select_track(player.info.start_track);
+        }
+    }
+
+    pub fn nsf_next_track(&mut self) {
+        if let Some(player) = &self.nsf {
+            let track = (player.track + 1) % player.track_count();
+            self.nsf_select_track(track);
+        }
+    }
+
+    pub fn nsf_previous_track(&mut self) {
+        if let Some(player) = &self.nsf {
+            let track = (player.track + player.track_count() - 1) % player.track_count();
+            self.nsf_select_track(track);
+        }
+    }
+
+    /// Called after every frame, advances to the next track once the current one is over
+    pub fn nsf_frame(&mut self) {
+        let frame_rate = self.region.frame_rate();
+        let Some(player) = &mut self.nsf else {
+            return;
+        };
+
+        player.frames += 1;
+
+        let finished = player
+            .track_length(player.track)
+            .is_some_and(|length| player.elapsed_ms(frame_rate) >= length);
+
+        if finished {
+            self.nsf_next_track();
+        } else if player.frames % frame_rate.round() as usize == 0 {
+            self.update_nsf_overlay();
+        }
+    }
+
+    /// Shows the tune information and the track list over the frame
+    pub fn update_nsf_overlay(&mut self) {
+        let frame_rate = self.region.frame_rate();
+        let Some(player) = &self.nsf else {
+            return;
+        };
+
+        let info = &player.info;
+        let mut lines = vec![
+            info.title.clone(),
+            info.artist.clone(),
+            info.copyright.clone(),
+            String::new(),
+        ];
+
+        let first = (player.track as usize)
+            .saturating_sub(VISIBLE_TRACKS / 2)
+            .min(info.tracks.len().saturating_sub(VISIBLE_TRACKS));
+
+        for (idx, track) in info
+            .tracks
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE_TRACKS)
+        {
+            let marker = if idx == player.track as usize {
+                '>'
+            } else {
+                ' '
+            };
+            let name = track.name.clone().unwrap_or_default();
+            let length = player
+                .track_length(idx as u8)
+                .map(format_time)
+                .unwrap_or_default();
+
+            lines.push(format!(
+                "{}{:02} {:<28.28}{:>5}",
+                marker,
+                idx + 1,
+                name,
+                length
+            ));
+        }
+
+        lines.push(String::new());
+
+        let length = player
+            .track_length(player.track)
+            .map(|length| format!(" / {}", format_time(length)))
+            .unwrap_or_default();
+        lines.push(format!(
+            "TRACK {}/{}  {}{}",
+            player.track + 1,
+            player.track_count(),
+            format_time(player.elapsed_ms(frame_rate)),
+            length
+        ));
+        lines.push("LEFT/RIGHT: PREVIOUS/NEXT TRACK".to_string());
+
+        self.overlay = lines;
+    }
+}
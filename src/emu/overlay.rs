@@ -0,0 +1,338 @@
+use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+
+use super::{Emulator, ORIGINAL_WIDTH};
+
+/// Width of a character cell in NES pixels
+const CHAR_WIDTH: usize = 6;
+
+/// Height of a character cell in NES pixels
+const CHAR_HEIGHT: usize = 9;
+
+/// Width of a glyph
+const GLYPH_WIDTH: usize = 5;
+
+/// Characters that fit in a line
+pub const OVERLAY_COLUMNS: usize = (ORIGINAL_WIDTH as usize - CHAR_WIDTH) / CHAR_WIDTH;
+
+/// 5x7 glyphs for the characters from ' ' to '_', lowercase letters use the uppercase glyphs
+const FONT: [[u8; 7]; 64] = [
+    // space
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000,
+    ],
+    // !
+    [
+        0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00000, 0b00100,
+    ],
+    // "
+    [
+        0b01010, 0b01010, 0b01010, 0b00000, 0b00000, 0b00000, 0b00000,
+    ],
+    // #
+    [
+        0b01010, 0b01010, 0b11111, 0b01010, 0b11111, 0b01010, 0b01010,
+    ],
+    // $
+    [
+        0b00100, 0b01111, 0b10100, 0b01110, 0b00101, 0b11110, 0b00100,
+    ],
+    // %
+    [
+        0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011,
+    ],
+    // &
+    [
+        0b01100, 0b10010, 0b10100, 0b01000, 0b10101, 0b10010, 0b01101,
+    ],
+    // '
+    [
+        0b00100, 0b00100, 0b01000, 0b00000, 0b00000, 0b00000, 0b00000,
+    ],
+    // (
+    [
+        0b00010, 0b00100, 0b01000, 0b01000, 0b01000, 0b00100, 0b00010,
+    ],
+    // )
+    [
+        0b01000, 0b00100, 0b00010, 0b00010, 0b00010, 0b00100, 0b01000,
+    ],
+    // *
+    [
+        0b00000, 0b00100, 0b10101, 0b01110, 0b10101, 0b00100, 0b00000,
+    ],
+    // +
+    [
+        0b00000, 0b00100, 0b00100, 0b11111, 0b00100, 0b00100, 0b00000,
+    ],
+    // ,
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b00100, 0b01000,
+    ],
+    // -
+    [
+        0b00000, 0b00000, 0b00000, 0b11111, 0b00000, 0b00000, 0b00000,
+    ],
+    // .
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100,
+    ],
+    // /
+    [
+        0b00000, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b00000,
+    ],
+    // 0
+    [
+        0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110,
+    ],
+    // 1
+    [
+        0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ],
+    // 2
+    [
+        0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111,
+    ],
+    // 3
+    [
+        0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110,
+    ],
+    // 4
+    [
+        0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010,
+    ],
+    // 5
+    [
+        0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110,
+    ],
+    // 6
+    [
+        0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110,
+    ],
+    // 7
+    [
+        0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000,
+    ],
+    // 8
+    [
+        0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110,
+    ],
+    // 9
+    [
+        0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100,
+    ],
+    // :
+    [
+        0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b01100, 0b00000,
+    ],
+    // ;
+    [
+        0b00000, 0b01100, 0b01100, 0b00000, 0b01100, 0b00100, 0b01000,
+    ],
+    // <
+    [
+        0b00010, 0b00100, 0b01000, 0b10000, 0b01000, 0b00100, 0b00010,
+    ],
+    // =
+    [
+        0b00000, 0b00000, 0b11111, 0b00000, 0b11111, 0b00000, 0b00000,
+    ],
+    // >
+    [
+        0b01000, 0b00100, 0b00010, 0b00001, 0b00010, 0b00100, 0b01000,
+    ],
+    // ?
+    [
+        0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b00000, 0b00100,
+    ],
+    // @
+    [
+        0b01110, 0b10001, 0b00001, 0b01101, 0b10101, 0b10101, 0b01110,
+    ],
+    // A
+    [
+        0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+    ],
+    // B
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110,
+    ],
+    // C
+    [
+        0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110,
+    ],
+    // D
+    [
+        0b11100, 0b10010, 0b10001, 0b10001, 0b10001, 0b10010, 0b11100,
+    ],
+    // E
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111,
+    ],
+    // F
+    [
+        0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000,
+    ],
+    // G
+    [
+        0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111,
+    ],
+    // H
+    [
+        0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001,
+    ],
+    // I
+    [
+        0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110,
+    ],
+    // J
+    [
+        0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100,
+    ],
+    // K
+    [
+        0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001,
+    ],
+    // L
+    [
+        0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111,
+    ],
+    // M
+    [
+        0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001,
+    ],
+    // N
+    [
+        0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001,
+    ],
+    // O
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ],
+    // P
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000,
+    ],
+    // Q
+    [
+        0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101,
+    ],
+    // R
+    [
+        0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001,
+    ],
+    // S
+    [
+        0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110,
+    ],
+    // T
+    [
+        0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100,
+    ],
+    // U
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110,
+    ],
+    // V
+    [
+        0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100,
+    ],
+    // W
+    [
+        0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010,
+    ],
+    // X
+    [
+        0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001,
+    ],
+    // Y
+    [
+        0b10001, 0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100,
+    ],
+    // Z
+    [
+        0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111,
+    ],
+    // [
+    [
+        0b01110, 0b01000, 0b01000, 0b01000, 0b01000, 0b01000, 0b01110,
+    ],
+    // \
+    [
+        0b00000, 0b10000, 0b01000, 0b00100, 0b00010, 0b00001, 0b00000,
+    ],
+    // ]
+    [
+        0b01110, 0b00010, 0b00010, 0b00010, 0b00010, 0b00010, 0b01110,
+    ],
+    // ^
+    [
+        0b00100, 0b01010, 0b10001, 0b00000, 0b00000, 0b00000, 0b00000,
+    ],
+    // _
+    [
+        0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b11111,
+    ],
+];
+
+fn draw_glyph(canvas: &mut Canvas<Window>, scale: u32, ch: char, x: usize, y: usize) {
+    let ch = ch.to_ascii_uppercase();
+    let idx = match ch {
+        ' '..='_' => ch as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+
+    for (row, bits) in FONT[idx].iter().enumerate() {
+        for col in 0..GLYPH_WIDTH {
+            if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                continue;
+            }
+
+            let rect = Rect::new(
+                ((x + col) * scale as usize) as i32,
+                ((y + row) * scale as usize) as i32,
+                scale,
+                scale,
+            );
+            canvas.fill_rect(rect).unwrap();
+        }
+    }
+}
+
+impl Emulator {
+    /// Draws the overlay text over the window, called before the frame is presented
+    pub fn draw_overlay(&mut self) {
+        let Some(display) = &mut self.display else {
+            return;
+        };
+
+        if self.overlay.is_empty() {
+            return;
+        }
+
+        let canvas = &mut display.canvas;
+
+        let height = (self.overlay.len() * CHAR_HEIGHT + CHAR_HEIGHT / 2) as u32;
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+        canvas
+            .fill_rect(Rect::new(
+                0,
+                0,
+                ORIGINAL_WIDTH * self.window_scale,
+                height * self.window_scale,
+            ))
+            .unwrap();
+
+        canvas.set_draw_color(Color::RGB(255, 255, 255));
+        for (line_idx, line) in self.overlay.iter().enumerate() {
+            for (col, ch) in line.chars().take(OVERLAY_COLUMNS).enumerate() {
+                draw_glyph(
+                    canvas,
+                    self.window_scale,
+                    ch,
+                    CHAR_WIDTH / 2 + col * CHAR_WIDTH,
+                    CHAR_HEIGHT / 2 + line_idx * CHAR_HEIGHT,
+                );
+            }
+        }
+    }
+}
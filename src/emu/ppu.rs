@@ -4,12 +4,17 @@ use modular_bitfield::{
     bitfield,
     specifiers::{B1, B3, B5},
 };
-use sdl2::{
-    pixels::Color,
-    rect::{Point, Rect},
+use sdl2::pixels::Color;
+
+use crate::{
+    nes::MirroringMode,
+    state::{StateError, StateReader, StateWriter},
 };
 
-use super::{Emulator, WINDOW_SCALE};
+use super::{
+    debugger::{Access, AddressSpace},
+    EmulationFault, Emulator, ORIGINAL_HEIGHT, ORIGINAL_WIDTH,
+};
 
 const PPUCTRL: u8 = 0;
 const PPUMASK: u8 = 1;
@@ -20,17 +25,8 @@ const PPUSCROLL: u8 = 5;
 const PPUADDR: u8 = 6;
 const PPUDATA: u8 = 7;
 
-const PALETTE: &[u8] = &[
-    84, 84, 84, 0, 30, 116, 8, 16, 144, 48, 0, 136, 68, 0, 100, 92, 0, 48, 84, 4, 0, 60, 24, 0, 32,
-    42, 0, 8, 58, 0, 0, 64, 0, 0, 60, 0, 0, 50, 60, 0, 0, 0, 0, 0, 0, 0, 0, 0, 152, 150, 152, 8,
-    76, 196, 48, 50, 236, 92, 30, 228, 136, 20, 176, 160, 20, 100, 152, 34, 32, 120, 60, 0, 84, 90,
-    0, 40, 114, 0, 8, 124, 0, 0, 118, 40, 0, 102, 120, 0, 0, 0, 0, 0, 0, 0, 0, 0, 236, 238, 236,
-    76, 154, 236, 120, 124, 236, 176, 98, 236, 228, 84, 236, 236, 88, 180, 236, 106, 100, 212, 136,
-    32, 160, 170, 0, 116, 196, 0, 76, 208, 32, 56, 204, 108, 56, 180, 204, 60, 60, 60, 0, 0, 0, 0,
-    0, 0, 236, 238, 236, 168, 204, 236, 188, 188, 236, 212, 178, 236, 236, 174, 236, 236, 174, 212,
-    236, 180, 176, 228, 196, 144, 204, 210, 120, 180, 222, 120, 168, 226, 144, 152, 226, 180, 160,
-    214, 228, 160, 162, 160, 0, 0, 0, 0, 0, 0,
-];
+/// Size of the RGB24 frame buffer
+pub const FRAME_BUFFER_SIZE: usize = (ORIGINAL_WIDTH * ORIGINAL_HEIGHT * 3) as usize;
 
 #[bitfield]
 #[derive(Debug, Clone)]
@@ -88,6 +84,16 @@ pub struct PPUData {
     cycle: usize,
     scanline: usize,
     vertical_blanking: bool,
+    /// Set by a PPUSTATUS read right before VBlank, which keeps the flag from being set
+    suppress_vblank: bool,
+    /// Output of the NMI line, an NMI happens on its rising edge
+    nmi_line: bool,
+    /// The CPU has to take an NMI before the next instruction
+    nmi_pending: bool,
+    /// Set from power on or reset until the pre-render line, PPUCTRL, PPUMASK, PPUSCROLL and PPUADDR
+    /// ignore writes meanwhile
+    /// https://www.nesdev.org/wiki/PPU_power_up_state
+    ignore_writes: bool,
     nametables: [[u8; 1024]; 4],
     control_reg: ControlReg,
     mask_reg: MaskReg,
@@ -96,11 +102,26 @@ pub struct PPUData {
     temp_vram_address: VRAMAddress,
     fine_x: u8,
     data_buffer: u8,
-    lsb_shift_reg: u8,
-    msb_shift_reg: u8,
+    /// Last value written to or read from a PPU register, write-only registers read back as it
+    /// https://www.nesdev.org/wiki/Open_bus_behavior#PPU_open_bus
+    io_latch: u8,
     palette_table: [u8; 32],
-    current_palette: u8,
-    current_sprite_index: u8,
+    /// Latches filled by the background fetches
+    bg_next_tile: u8,
+    bg_next_attrib: u8,
+    bg_next_pattern_lo: u8,
+    bg_next_pattern_hi: u8,
+    /// The high byte holds the tile being drawn, the low byte the next one
+    bg_pattern_lo_shift: u16,
+    bg_pattern_hi_shift: u16,
+    bg_attrib_lo_shift: u16,
+    bg_attrib_hi_shift: u16,
+    oam: [u8; 256],
+    oam_address: u8,
+    /// RGB24 pixels of the frame being drawn
+    pub frame_buffer: Box<[u8]>,
+    /// Palette indices of the frame being drawn, with the emphasis bits above the 6-bit index
+    pub index_buffer: Box<[u16]>,
 }
 
 impl PPUData {
@@ -110,6 +131,10 @@ impl PPUData {
             cycle: 0,
             scanline: 0,
             vertical_blanking: false,
+            suppress_vblank: false,
+            nmi_line: false,
+            nmi_pending: false,
+            ignore_writes: true,
             nametables: [[0; 1024]; 4],
             control_reg: ControlReg::new(),
             mask_reg: MaskReg::new(),
@@ -118,56 +143,185 @@ impl PPUData {
             temp_vram_address: VRAMAddress::new(),
             fine_x: 0,
             data_buffer: 0,
-            lsb_shift_reg: 0,
-            msb_shift_reg: 0,
+            io_latch: 0,
             palette_table: [0; 32],
-            current_palette: 0,
-            current_sprite_index: 0,
+            bg_next_tile: 0,
+            bg_next_attrib: 0,
+            bg_next_pattern_lo: 0,
+            bg_next_pattern_hi: 0,
+            bg_pattern_lo_shift: 0,
+            bg_pattern_hi_shift: 0,
+            bg_attrib_lo_shift: 0,
+            bg_attrib_hi_shift: 0,
+            oam: [0; 256],
+            oam_address: 0,
+            frame_buffer: vec![0; FRAME_BUFFER_SIZE].into_boxed_slice(),
+            index_buffer: vec![0; (ORIGINAL_WIDTH * ORIGINAL_HEIGHT) as usize].into_boxed_slice(),
+        }
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.even_frame);
+        writer.write_usize(self.cycle);
+        writer.write_usize(self.scanline);
+        writer.write_bool(self.vertical_blanking);
+        writer.write_bool(self.suppress_vblank);
+        writer.write_bool(self.ignore_writes);
+        writer.write_bool(self.nmi_line);
+        writer.write_bool(self.nmi_pending);
+        for nametable in &self.nametables {
+            writer.write_bytes(nametable);
+        }
+        writer.write_bytes(&self.control_reg.bytes);
+        writer.write_bytes(&self.mask_reg.bytes);
+        writer.write_bool(self.second_byte);
+        writer.write_bytes(&self.vram_address.bytes);
+        writer.write_bytes(&self.temp_vram_address.bytes);
+        writer.write_u8(self.fine_x);
+        writer.write_u8(self.data_buffer);
+        writer.write_u8(self.io_latch);
+        writer.write_bytes(&self.palette_table);
+        writer.write_u8(self.bg_next_tile);
+        writer.write_u8(self.bg_next_attrib);
+        writer.write_u8(self.bg_next_pattern_lo);
+        writer.write_u8(self.bg_next_pattern_hi);
+        writer.write_u16(self.bg_pattern_lo_shift);
+        writer.write_u16(self.bg_pattern_hi_shift);
+        writer.write_u16(self.bg_attrib_lo_shift);
+        writer.write_u16(self.bg_attrib_hi_shift);
+        writer.write_bytes(&self.oam);
+        writer.write_u8(self.oam_address);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.even_frame = reader.read_bool()?;
+        self.cycle = reader.read_usize()?;
+        self.scanline = reader.read_usize()?;
+        self.vertical_blanking = reader.read_bool()?;
+        self.suppress_vblank = reader.read_bool()?;
+        self.ignore_writes = reader.read_bool()?;
+        self.nmi_line = reader.read_bool()?;
+        self.nmi_pending = reader.read_bool()?;
+        for nametable in self.nametables.iter_mut() {
+            reader.read_into(nametable)?;
         }
+        reader.read_into(&mut self.control_reg.bytes)?;
+        reader.read_into(&mut self.mask_reg.bytes)?;
+        self.second_byte = reader.read_bool()?;
+        reader.read_into(&mut self.vram_address.bytes)?;
+        reader.read_into(&mut self.temp_vram_address.bytes)?;
+        self.fine_x = reader.read_u8()?;
+        self.data_buffer = reader.read_u8()?;
+        self.io_latch = reader.read_u8()?;
+        reader.read_into(&mut self.palette_table)?;
+        self.bg_next_tile = reader.read_u8()?;
+        self.bg_next_attrib = reader.read_u8()?;
+        self.bg_next_pattern_lo = reader.read_u8()?;
+        self.bg_next_pattern_hi = reader.read_u8()?;
+        self.bg_pattern_lo_shift = reader.read_u16()?;
+        self.bg_pattern_hi_shift = reader.read_u16()?;
+        self.bg_attrib_lo_shift = reader.read_u16()?;
+        self.bg_attrib_hi_shift = reader.read_u16()?;
+        reader.read_into(&mut self.oam)?;
+        self.oam_address = reader.read_u8()?;
+        Ok(())
+    }
+}
+
+/// Maps a $3F00-$3FFF address to its palette RAM entry
+/// $3F10/$3F14/$3F18/$3F1C mirror the background entries $3F00/$3F04/$3F08/$3F0C
+/// https://www.nesdev.org/wiki/PPU_palettes#Memory_Map
+fn palette_index(addr: u16) -> usize {
+    let off = addr as usize & 0x1F;
+    if off & 0x13 == 0x10 {
+        off & !0x10
+    } else {
+        off
     }
 }
 
 impl Emulator {
     pub fn ppu_read_reg(&mut self, reg: u8) -> u8 {
         assert!(reg < 8);
-        match reg {
-            PPUCTRL | PPUMASK | OAMADDR | PPUSCROLL | PPUADDR => 0,
+        let val = match reg {
+            PPUCTRL | PPUMASK | OAMADDR | PPUSCROLL | PPUADDR => self.ppu.io_latch,
+            OAMDATA => self.ppu.oam[self.ppu.oam_address as usize],
             PPUSTATUS => {
+                // https://www.nesdev.org/wiki/PPU_frame_timing#VBL_Flag_Timing
+                let on_vblank_line = self.ppu.scanline == self.region.vblank_scanline();
+
+                // reading a dot before the flag is set returns it clear and keeps it from being set
+                if on_vblank_line && self.ppu.cycle == 1 {
+                    self.ppu.suppress_vblank = true;
+                }
+
                 let mut res = 0;
                 if self.ppu.vertical_blanking {
                     res |= 1 << 7;
                     self.ppu.vertical_blanking = false;
                 }
 
+                // reading as the flag gets set returns it but cancels the NMI
+                if on_vblank_line && (2..=3).contains(&self.ppu.cycle) {
+                    self.ppu.nmi_pending = false;
+                }
+
                 self.ppu.second_byte = false;
+                self.update_nmi_line();
 
-                res
+                // the low bits aren't driven and come from the open bus
+                res | self.ppu.io_latch & 0x1F
             }
             PPUDATA => {
-                assert!(!self.ppu.second_byte);
-
-                let mut temp_addr = u16::from_ne_bytes(self.ppu.vram_address.bytes);
-                let val = self.ppu_read(temp_addr);
+                let addr = u16::from_ne_bytes(self.ppu.vram_address.bytes) & 0x3FFF;
 
-                temp_addr += [1, 32][self.ppu.control_reg.vram_increment() as usize];
-                self.ppu.vram_address = VRAMAddress::from_bytes(temp_addr.to_ne_bytes());
-
-                let ret = if temp_addr > 0x3f00 {
-                    val
+                let ret = if addr >= 0x3F00 {
+                    // palette reads aren't buffered, the buffer gets the nametable byte underneath
+                    self.ppu.data_buffer = self.ppu_read(addr - 0x1000);
+                    self.ppu_read(addr)
                 } else {
-                    self.ppu.data_buffer
+                    let ret = self.ppu.data_buffer;
+                    self.ppu.data_buffer = self.ppu_read(addr);
+                    ret
                 };
+                self.debug_access(AddressSpace::Ppu, addr, Access::Read, ret);
 
-                self.ppu.data_buffer = val;
+                self.increment_vram_after_data_access();
 
                 ret
             }
             _ => unreachable!(),
+        };
+
+        self.ppu.io_latch = val;
+        val
+    }
+
+    /// Advances the VRAM address after a PPUDATA access
+    fn increment_vram_after_data_access(&mut self) {
+        let rendering_line =
+            self.ppu.scanline < 240 || self.ppu.scanline == self.region.prerender_scanline();
+
+        if rendering_line && self.rendering_enabled() {
+            // during rendering the access glitches into both scroll increments
+            self.increment_vram_x();
+            self.increment_vram_y();
+        } else {
+            let addr = u16::from_ne_bytes(self.ppu.vram_address.bytes);
+            let increment = [1, 32][self.ppu.control_reg.vram_increment() as usize];
+            let addr = addr.wrapping_add(increment) & 0x7FFF;
+            self.ppu.vram_address = VRAMAddress::from_bytes(addr.to_ne_bytes());
         }
     }
 
     pub fn ppu_write_reg(&mut self, reg: u8, val: u8) {
         assert!(reg < 8);
+        self.ppu.io_latch = val;
+
+        if self.ppu.ignore_writes && matches!(reg, PPUCTRL | PPUMASK | PPUSCROLL | PPUADDR) {
+            return;
+        }
+
         match reg {
             PPUSTATUS => {}
             PPUCTRL => {
@@ -178,6 +332,9 @@ impl Emulator {
                 self.ppu
                     .temp_vram_address
                     .set_nametable_y(self.ppu.control_reg.nametable_y());
+
+                // enabling NMIs during VBlank triggers one immediately
+                self.update_nmi_line();
             }
             PPUMASK => {
                 self.ppu.mask_reg = MaskReg::from_bytes([val]);
@@ -189,21 +346,24 @@ impl Emulator {
                     self.ppu.vram_address = self.ppu.temp_vram_address.clone();
                     self.ppu.second_byte = false;
                 } else {
-                    self.ppu.temp_vram_address = VRAMAddress::from_bytes([0, val]);
+                    // the high byte only has 6 bits, bit 14 gets cleared
+                    self.ppu.temp_vram_address =
+                        VRAMAddress::from_bytes([self.ppu.temp_vram_address.bytes[0], val & 0x3F]);
                     self.ppu.second_byte = true;
                 };
             }
             PPUDATA => {
-                assert!(!self.ppu.second_byte);
+                let addr = u16::from_ne_bytes(self.ppu.vram_address.bytes) & 0x3FFF;
+                self.debug_access(AddressSpace::Ppu, addr, Access::Write, val);
+                self.ppu_write(addr, val);
 
-                let mut temp_addr = u16::from_ne_bytes(self.ppu.vram_address.bytes);
-                self.ppu_write(temp_addr, val);
-
-                temp_addr += [1, 32][self.ppu.control_reg.vram_increment() as usize];
-                self.ppu.vram_address = VRAMAddress::from_bytes(temp_addr.to_ne_bytes());
+                self.increment_vram_after_data_access();
+            }
+            OAMADDR => self.ppu.oam_address = val,
+            OAMDATA => {
+                self.ppu.oam[self.ppu.oam_address as usize] = val;
+                self.ppu.oam_address = self.ppu.oam_address.wrapping_add(1);
             }
-            // TODO
-            OAMADDR | OAMDATA => {}
             PPUSCROLL => {
                 if self.ppu.second_byte {
                     self.ppu.temp_vram_address.set_coarse_y(val >> 3);
@@ -219,66 +379,95 @@ impl Emulator {
         }
     }
 
-    fn ppu_read(&self, addr: u16) -> u8 {
-        if addr < 0x2000 {
-            self.mapper.read_ppu(addr).unwrap()
-        } else if addr < 0x3000 {
-            let rel = addr as usize - 0x2000;
-            let nametable = rel / 0x400;
-            let off = rel & 0x3FF;
+    /// Copies a page of CPU memory into OAM($4014)
+    pub fn oam_dma(&mut self, page: u8) {
+        // the CPU is halted for a cycle, and one more to align with the reads on even cycles
+        // https://www.nesdev.org/wiki/DMA#OAM_DMA
+        self.tick();
+        if self.cpu.cycles % 2 == 1 {
+            self.tick();
+        }
 
-            self.ppu.nametables[nametable][off]
-        } else if addr < 0x3EFF {
-            let rel = addr as usize - 0x3000;
-            let nametable = rel / 0x400;
-            let off = rel & 0x3FF;
+        let base = (page as u16) << 8;
+        for off in 0..256 {
+            let val = self.read(base + off);
+            self.write(0x2004, val);
+        }
+    }
+
+    /// Maps a nametable address to the physical nametable and the offset inside it
+    fn nametable_location(&self, addr: u16) -> (usize, usize) {
+        let rel = (addr as usize - 0x2000) & 0xFFF;
+        let nametable = rel / 0x400;
+        let off = rel & 0x3FF;
+
+        let physical = match self.mapper.mirroring_mode() {
+            MirroringMode::Horizontal => nametable / 2,
+            MirroringMode::Vertical => nametable % 2,
+            MirroringMode::SingleScreenLower => 0,
+            MirroringMode::SingleScreenUpper => 1,
+            MirroringMode::FourScreen => nametable,
+        };
+
+        (physical, off)
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        if addr >= 0x2000 {
+            return self.ppu_peek(addr);
+        }
+
+        match self.mapper.read_ppu(addr) {
+            Ok(val) => val,
+            Err(err) => {
+                self.raise_fault(EmulationFault::from_mapper_error(
+                    err,
+                    AddressSpace::Ppu,
+                    addr,
+                    None,
+                ));
+
+                // the multiplexed address/data lines still hold the low byte of the address
+                addr as u8
+            }
+        }
+    }
+
+    /// Reads the PPU address space for debugging without going through PPUDATA
+    pub fn ppu_peek(&self, addr: u16) -> u8 {
+        let addr = addr & 0x3FFF;
 
+        if addr < 0x2000 {
+            self.mapper.read_ppu(addr).unwrap_or(addr as u8)
+        } else if addr < 0x3F00 {
+            let (nametable, off) = self.nametable_location(addr);
             self.ppu.nametables[nametable][off]
-        } else if addr < 0x3FFF {
-            todo!()
         } else {
-            unreachable!();
+            // palette entries are 6 bits wide, the upper bits come from the open bus
+            self.ppu.palette_table[palette_index(addr)] | self.ppu.io_latch & 0xC0
         }
     }
 
     fn ppu_write(&mut self, addr: u16, val: u8) {
         if addr < 0x2000 {
-            self.mapper.write_ppu(addr, val).unwrap();
-        } else if addr < 0x3000 {
-            let rel = addr as usize - 0x2000;
-            let nametable = rel / 0x400;
-            let off = rel & 0x3FF;
-
-            self.ppu.nametables[nametable][off] = val;
-        } else if addr < 0x3EFF {
-            let rel = addr as usize - 0x3000;
-            let nametable = rel / 0x400;
-            let off = rel & 0x3FF;
-
+            if let Err(err) = self.mapper.write_ppu(addr, val) {
+                self.raise_fault(EmulationFault::from_mapper_error(
+                    err,
+                    AddressSpace::Ppu,
+                    addr,
+                    Some(val),
+                ));
+            }
+        } else if addr < 0x3F00 {
+            let (nametable, off) = self.nametable_location(addr);
             self.ppu.nametables[nametable][off] = val;
-        } else if addr < 0x3FFF {
-            let off = addr & 0x1F;
-            self.ppu.palette_table[off as usize] = val;
+        } else if addr < 0x4000 {
+            self.ppu.palette_table[palette_index(addr)] = val & 0x3F;
         } else {
             unreachable!();
         }
     }
 
-    /// Retrieves a tile from the pattern table
-    fn get_sprite_line(&self, right: bool, idx: u8, fine_y: u8) -> (u8, u8) {
-        assert!(fine_y < 8);
-
-        let idx = idx as u16;
-        let fine_y = fine_y as u16;
-
-        let pattern_table_addr = if right { 0x1000 } else { 0x0 };
-        let base_addr = pattern_table_addr + idx * 16 + fine_y;
-
-        let (lsb_byte, msb_byte) = (self.ppu_read(base_addr), self.ppu_read(base_addr + 8));
-
-        (lsb_byte, msb_byte)
-    }
-
     fn increment_vram_x(&mut self) {
         if self.ppu.mask_reg.show_background() == 0 && self.ppu.mask_reg.show_sprites() == 0 {
             return;
@@ -353,114 +542,261 @@ impl Emulator {
             .set_fine_y(self.ppu.temp_vram_address.fine_y());
     }
 
-    pub fn clock_ppu(&mut self) {
-        if self.ppu.scanline == 0 && self.ppu.cycle == 0 {
-            self.ppu.vertical_blanking = false;
+    /// Recomputes the NMI output, a rising edge makes the CPU take an NMI
+    fn update_nmi_line(&mut self) {
+        let line = self.ppu.vertical_blanking && self.ppu.control_reg.generate_nmi() > 0;
+        if line && !self.ppu.nmi_line {
+            self.ppu.nmi_pending = true;
         }
+        self.ppu.nmi_line = line;
+    }
 
-        if self.ppu.scanline < 240 {
-            match self.ppu.cycle.cmp(&256) {
-                std::cmp::Ordering::Equal => {
-                    self.increment_vram_y();
-                }
-                std::cmp::Ordering::Less => {
-                    let x = self.ppu.cycle;
-                    let y = self.ppu.scanline;
+    /// The reset button clears the registers that aren't counters or memory
+    /// https://www.nesdev.org/wiki/PPU_power_up_state
+    pub fn reset_ppu(&mut self) {
+        self.ppu.control_reg = ControlReg::new();
+        self.ppu.mask_reg = MaskReg::new();
+        self.ppu.second_byte = false;
+        self.ppu.temp_vram_address = VRAMAddress::new();
+        self.ppu.fine_x = 0;
+        self.ppu.data_buffer = 0;
+        self.ppu.ignore_writes = true;
+
+        self.update_nmi_line();
+    }
 
-                    if x != 0 && x % 8 == 0 {
-                        self.increment_vram_x();
-                        let vram_addr = u16::from_ne_bytes(self.ppu.vram_address.bytes);
+    /// Returns true once for every NMI the PPU raised
+    pub fn take_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.ppu.nmi_pending)
+    }
 
-                        let sprite_addr = 0x2000 | vram_addr & 0xFFF;
-                        self.ppu.current_sprite_index = self.ppu_read(sprite_addr);
+    /// Whether the PPU raised an NMI the CPU hasn't taken yet
+    pub fn nmi_pending(&self) -> bool {
+        self.ppu.nmi_pending
+    }
 
-                        let (lsb, msb) = self.get_sprite_line(
-                            self.ppu.control_reg.background_pattern_table_address() > 0,
-                            self.ppu.current_sprite_index,
-                            self.ppu.vram_address.fine_y(),
-                        );
+    /// Value left on the bus between the CPU and the PPU by the last register access
+    pub fn ppu_io_latch(&self) -> u8 {
+        self.ppu.io_latch
+    }
 
-                        self.ppu.lsb_shift_reg = lsb;
-                        self.ppu.msb_shift_reg = msb;
+    fn rendering_enabled(&self) -> bool {
+        self.ppu.mask_reg.show_background() > 0 || self.ppu.mask_reg.show_sprites() > 0
+    }
 
-                        let attrib_addr = 0x23C0
-                            | 0b1111000000
-                            | (self.ppu.vram_address.coarse_x() >> 2) as u16
-                            | ((self.ppu.vram_address.coarse_y() >> 2) << 3) as u16
-                            | (self.ppu.vram_address.nametable_x() as u16) << 10
-                            | (self.ppu.vram_address.nametable_y() as u16) << 11;
+    /// Moves the background shift registers by a pixel
+    fn shift_background(&mut self) {
+        if self.ppu.mask_reg.show_background() == 0 {
+            return;
+        }
 
-                        let mut attrib = self.ppu_read(attrib_addr);
-                        if self.ppu.vram_address.coarse_y() & 0b10 > 0 {
-                            attrib >>= 4;
-                        }
+        self.ppu.bg_pattern_lo_shift <<= 1;
+        self.ppu.bg_pattern_hi_shift <<= 1;
+        self.ppu.bg_attrib_lo_shift <<= 1;
+        self.ppu.bg_attrib_hi_shift <<= 1;
+    }
 
-                        if self.ppu.vram_address.coarse_x() & 0b10 > 0 {
-                            attrib >>= 2;
-                        }
+    /// Loads the fetched tile into the low bytes of the shift registers
+    fn reload_background_shifters(&mut self) {
+        self.ppu.bg_pattern_lo_shift =
+            self.ppu.bg_pattern_lo_shift & 0xFF00 | self.ppu.bg_next_pattern_lo as u16;
+        self.ppu.bg_pattern_hi_shift =
+            self.ppu.bg_pattern_hi_shift & 0xFF00 | self.ppu.bg_next_pattern_hi as u16;
+
+        // the attribute applies to the whole tile, so its bits are repeated 8 times
+        let attrib = self.ppu.bg_next_attrib;
+        self.ppu.bg_attrib_lo_shift =
+            self.ppu.bg_attrib_lo_shift & 0xFF00 | if attrib & 0b01 > 0 { 0xFF } else { 0 };
+        self.ppu.bg_attrib_hi_shift =
+            self.ppu.bg_attrib_hi_shift & 0xFF00 | if attrib & 0b10 > 0 { 0xFF } else { 0 };
+    }
 
-                        self.ppu.current_palette = attrib & 0b11;
-                    }
+    /// Background fetches, every tile takes 8 dots: nametable, attribute, pattern low, pattern high
+    /// https://www.nesdev.org/wiki/PPU_rendering#Cycles_1-256
+    fn fetch_background(&mut self, dot: usize) {
+        let vram_addr = u16::from_ne_bytes(self.ppu.vram_address.bytes);
 
-                    let color_idx =
-                        (self.ppu.msb_shift_reg >> 7) * 2 + (self.ppu.lsb_shift_reg >> 7);
-                    self.ppu.msb_shift_reg <<= 1;
-                    self.ppu.lsb_shift_reg <<= 1;
+        match (dot - 1) % 8 {
+            0 => {
+                self.reload_background_shifters();
+                self.ppu.bg_next_tile = self.ppu_read(0x2000 | vram_addr & 0xFFF);
+            }
+            2 => {
+                let attrib_addr = 0x23C0
+                    | (vram_addr & 0x0C00)
+                    | ((self.ppu.vram_address.coarse_y() >> 2) << 3) as u16
+                    | (self.ppu.vram_address.coarse_x() >> 2) as u16;
+
+                let mut attrib = self.ppu_read(attrib_addr);
+                if self.ppu.vram_address.coarse_y() & 0b10 > 0 {
+                    attrib >>= 4;
+                }
+                if self.ppu.vram_address.coarse_x() & 0b10 > 0 {
+                    attrib >>= 2;
+                }
 
-                    let palette_table_idx = self.ppu.current_palette * 4 + color_idx;
+                self.ppu.bg_next_attrib = attrib & 0b11;
+            }
+            4 | 6 => {
+                let pattern_table = if self.ppu.control_reg.background_pattern_table_address() > 0 {
+                    0x1000
+                } else {
+                    0
+                };
+                let addr = pattern_table
+                    + self.ppu.bg_next_tile as u16 * 16
+                    + self.ppu.vram_address.fine_y() as u16;
 
-                    let idx = self.ppu.palette_table[palette_table_idx as usize] as usize;
+                if (dot - 1) % 8 == 4 {
+                    self.ppu.bg_next_pattern_lo = self.ppu_read(addr);
+                } else {
+                    self.ppu.bg_next_pattern_hi = self.ppu_read(addr + 8);
+                }
+            }
+            7 => self.increment_vram_x(),
+            _ => {}
+        }
+    }
 
-                    let (r, g, b) = (PALETTE[idx * 3], PALETTE[idx * 3 + 1], PALETTE[idx * 3 + 2]);
+    /// Background pixel selected by fine X, returns the palette and the color inside it
+    fn background_pixel(&self, x: usize) -> (u8, u8) {
+        if self.ppu.mask_reg.show_background() == 0 {
+            return (0, 0);
+        }
 
-                    self.draw_pixel(x, y, Color::RGB(r, g, b));
-                }
-                _ => {}
+        // the leftmost 8 pixels can be hidden to mask scrolling artifacts
+        if x < 8 && self.ppu.mask_reg.show_background_leftmost() == 0 {
+            return (0, 0);
+        }
+
+        let mux = 0x8000 >> self.ppu.fine_x;
+        let bit = |shifter: u16| (shifter & mux > 0) as u8;
+
+        let color = bit(self.ppu.bg_pattern_hi_shift) << 1 | bit(self.ppu.bg_pattern_lo_shift);
+        let palette = bit(self.ppu.bg_attrib_hi_shift) << 1 | bit(self.ppu.bg_attrib_lo_shift);
+
+        (palette, color)
+    }
+
+    pub fn clock_ppu(&mut self) {
+        let prerender_scanline = self.region.prerender_scanline();
+        let dot = self.ppu.cycle;
+
+        // https://www.nesdev.org/wiki/PPU_frame_timing
+        if self.ppu.scanline == self.region.vblank_scanline() && dot == 1 {
+            if !self.ppu.suppress_vblank {
+                self.ppu.vertical_blanking = true;
+                self.update_nmi_line();
             }
+            self.ppu.suppress_vblank = false;
+            self.present_frame();
+        }
+
+        if self.ppu.scanline == prerender_scanline && dot == 1 {
+            self.ppu.vertical_blanking = false;
+            self.ppu.ignore_writes = false;
+            self.update_nmi_line();
         }
 
-        if self.ppu.cycle == 257 {
-            self.transfer_vram_x();
+        // Visible scanlines and the pre-render line run the same fetches
+        if self.ppu.scanline < 240 || self.ppu.scanline == prerender_scanline {
+            if (2..=257).contains(&dot) || (321..=337).contains(&dot) {
+                self.shift_background();
+                if self.rendering_enabled() {
+                    self.fetch_background(dot);
+                }
+            }
+
+            if self.rendering_enabled() {
+                match dot {
+                    256 => self.increment_vram_y(),
+                    257 => {
+                        self.reload_background_shifters();
+                        self.transfer_vram_x();
+                    }
+                    // unused nametable fetches at the end of the line
+                    338 | 340 => {
+                        let vram_addr = u16::from_ne_bytes(self.ppu.vram_address.bytes);
+                        self.ppu.bg_next_tile = self.ppu_read(0x2000 | vram_addr & 0xFFF);
+                    }
+                    280..=304 if self.ppu.scanline == prerender_scanline => self.transfer_vram_y(),
+                    _ => {}
+                }
+            }
         }
 
-        if self.ppu.scanline == 261 && self.ppu.cycle >= 280 && self.ppu.cycle <= 304 {
-            self.transfer_vram_y();
+        if self.ppu.scanline < 240 && (1..=256).contains(&dot) {
+            let (palette, color) = self.background_pixel(dot - 1);
+
+            // color 0 of every palette shows the backdrop
+            let palette_table_idx = if color == 0 { 0 } else { palette * 4 + color };
+            let mut idx = self.ppu.palette_table[palette_table_idx as usize] as usize;
+
+            // greyscale keeps only the grey column of the palette
+            if self.ppu.mask_reg.greyscale() > 0 {
+                idx &= 0x30;
+            }
+
+            let (x, y) = (dot - 1, self.ppu.scanline);
+            let emphasis = self.emphasis();
+            self.ppu.index_buffer[y * ORIGINAL_WIDTH as usize + x] =
+                idx as u16 | (emphasis as u16) << 6;
+
+            let [r, g, b] = self.palette.color(idx, emphasis);
+            self.draw_pixel(x, y, Color::RGB(r, g, b));
         }
 
         self.ppu.cycle += 1;
+
+        // odd frames jump from dot 339 of the pre-render line straight to the first visible dot
+        if self.ppu.scanline == prerender_scanline
+            && self.ppu.cycle == 340
+            && !self.ppu.even_frame
+            && self.rendering_enabled()
+            && self.region.skips_odd_frame_dot()
+        {
+            self.ppu.cycle = 341;
+        }
+
         if self.ppu.cycle > 340 {
             self.ppu.cycle = 0;
             self.ppu.scanline += 1;
         }
 
-        if self.ppu.scanline == 241 && self.ppu.cycle == 0 {
-            self.ppu.vertical_blanking = true;
-            if self.ppu.control_reg.generate_nmi() > 0 {
-                self.nmi();
-            }
-            self.canvas.present();
-        }
-
-        if self.ppu.scanline > 261 {
+        if self.ppu.scanline > prerender_scanline {
             self.ppu.scanline = 0;
+            self.ppu.even_frame = !self.ppu.even_frame;
             self.frame_complete = true;
-            println!("FRAME DONE");
         }
     }
 
+    /// PPUMASK emphasis bits as red, green and blue
+    fn emphasis(&self) -> u8 {
+        let mask = &self.ppu.mask_reg;
+        let (mut emphasize_red, mut emphasize_green) =
+            (mask.emphasize_red(), mask.emphasize_green());
+        if self.region.swaps_red_green_emphasis() {
+            (emphasize_red, emphasize_green) = (emphasize_green, emphasize_red);
+        }
+        mask.emphasize_blue() << 2 | emphasize_green << 1 | emphasize_red
+    }
+
     fn draw_pixel(&mut self, x: usize, y: usize, color: Color) {
-        self.canvas.set_draw_color(color);
-        if WINDOW_SCALE == 1 {
-            let point = Point::new(x as i32, y as i32);
-            self.canvas.draw_point(point).unwrap();
-        } else {
-            let rect = Rect::new(
-                x as i32 * WINDOW_SCALE as i32,
-                y as i32 * WINDOW_SCALE as i32,
-                WINDOW_SCALE,
-                WINDOW_SCALE,
-            );
-            self.canvas.fill_rect(rect).unwrap();
+        if x >= ORIGINAL_WIDTH as usize || y >= ORIGINAL_HEIGHT as usize {
+            return;
         }
+
+        let off = (y * ORIGINAL_WIDTH as usize + x) * 3;
+        self.ppu.frame_buffer[off..off + 3].copy_from_slice(&[color.r, color.g, color.b]);
+    }
+
+    /// Current scanline and dot
+    pub fn ppu_position(&self) -> (usize, usize) {
+        (self.ppu.scanline, self.ppu.cycle)
+    }
+
+    /// RGB24 pixels of the last frame
+    pub fn frame_buffer(&self) -> &[u8] {
+        &self.ppu.frame_buffer
     }
 }
@@ -0,0 +1,89 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{ppu::PPUData, Emulator};
+
+/// Contents of the internal RAM at power on, real consoles power on with mostly but not entirely
+/// predictable values
+/// https://www.nesdev.org/wiki/CPU_power_up_state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PowerOnRam {
+    #[default]
+    Zero,
+
+    Ones,
+
+    /// Four 0x00 bytes followed by four 0xFF bytes, the pattern many consoles come close to
+    Alternating,
+
+    /// Different on every power on
+    Random,
+}
+
+impl PowerOnRam {
+    pub fn from_name(name: &str) -> Option<PowerOnRam> {
+        match name.to_ascii_lowercase().as_str() {
+            "zero" => Some(PowerOnRam::Zero),
+            "ones" => Some(PowerOnRam::Ones),
+            "alternating" => Some(PowerOnRam::Alternating),
+            "random" => Some(PowerOnRam::Random),
+            _ => None,
+        }
+    }
+
+    fn fill(&self, ram: &mut [u8]) {
+        match self {
+            PowerOnRam::Zero => ram.fill(0),
+            PowerOnRam::Ones => ram.fill(0xFF),
+            PowerOnRam::Alternating => {
+                for (off, val) in ram.iter_mut().enumerate() {
+                    *val = if off & 4 == 0 { 0x00 } else { 0xFF };
+                }
+            }
+            PowerOnRam::Random => {
+                let mut state = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos() as u64
+                    | 1;
+
+                // https://en.wikipedia.org/wiki/Xorshift
+                for val in ram.iter_mut() {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    *val = state as u8;
+                }
+            }
+        }
+    }
+}
+
+impl Emulator {
+    /// Pressing the reset button, the CPU runs its reset sequence without clearing the registers
+    /// and the PPU ignores most register writes until the next frame. RAM and the cartridge are
+    /// left alone.
+    /// https://www.nesdev.org/wiki/CPU_power_up_state#At_reset
+    pub fn soft_reset(&mut self) {
+        self.reset_ppu();
+        self.reset_cpu();
+        self.dismiss_fault();
+    }
+
+    /// Turning the console off and on, everything goes back to its power-on state
+    pub fn power_cycle(&mut self) {
+        self.power_on_ram.fill(&mut self.internal_ram);
+        self.ppu = PPUData::new();
+        self.mapper.power_cycle();
+        self.cycle_counter = 0;
+        self.frame_complete = false;
+
+        self.power_on_cpu();
+        self.dismiss_fault();
+    }
+
+    /// Sets the RAM contents of the next power on, the current contents are replaced right away
+    pub fn set_power_on_ram(&mut self, pattern: PowerOnRam) {
+        self.power_on_ram = pattern;
+        self.power_on_ram.fill(&mut self.internal_ram);
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::VecDeque;
+
+use super::Emulator;
+
+/// Default number of frames between two rewind snapshots
+pub const DEFAULT_REWIND_INTERVAL: usize = 4;
+
+/// Default memory budget of the rewind buffer in bytes
+pub const DEFAULT_REWIND_BUDGET: usize = 32 * 1024 * 1024;
+
+/// Token bit marking a run of zero bytes, otherwise the token is followed by literal bytes
+const ZERO_RUN: u8 = 0x80;
+
+/// Longest run a single token can describe
+const MAX_RUN: usize = 0x80;
+
+/// Ring buffer of snapshots, only the newest one is stored in full,
+/// older ones are XOR deltas against their successor compressed with RLE
+pub struct RewindBuffer {
+    interval: usize,
+    budget: usize,
+    frames: usize,
+    latest: Option<Vec<u8>>,
+    deltas: VecDeque<Vec<u8>>,
+    /// Size of all the deltas in bytes
+    deltas_size: usize,
+}
+
+impl Default for RewindBuffer {
+    fn default() -> RewindBuffer {
+        RewindBuffer::new(DEFAULT_REWIND_INTERVAL, DEFAULT_REWIND_BUDGET)
+    }
+}
+
+impl RewindBuffer {
+    pub fn new(interval: usize, budget: usize) -> RewindBuffer {
+        RewindBuffer {
+            interval: interval.max(1),
+            budget,
+            frames: 0,
+            latest: None,
+            deltas: VecDeque::new(),
+            deltas_size: 0,
+        }
+    }
+
+    /// Counts a frame, returns true when a snapshot should be pushed
+    fn tick(&mut self) -> bool {
+        self.frames += 1;
+        if self.frames >= self.interval {
+            self.frames = 0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn push(&mut self, state: Vec<u8>) {
+        if let Some(previous) = self.latest.take() {
+            let delta = encode_delta(&previous, &state);
+            self.deltas_size += delta.len();
+            self.deltas.push_back(delta);
+        }
+        self.latest = Some(state);
+
+        // Forget the oldest states until everything fits
+        let latest_size = self.latest.as_ref().map_or(0, Vec::len);
+        while self.deltas_size + latest_size > self.budget {
+            match self.deltas.pop_front() {
+                Some(delta) => self.deltas_size -= delta.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Returns the newest snapshot and steps back to the one before it,
+    /// the oldest snapshot is returned repeatedly once the buffer is exhausted
+    fn step_back(&mut self) -> Option<Vec<u8>> {
+        let state = self.latest.take()?;
+
+        self.latest = Some(match self.deltas.pop_back() {
+            Some(delta) => {
+                self.deltas_size -= delta.len();
+                decode_delta(&delta, &state)
+            }
+            None => state.clone(),
+        });
+
+        self.frames = 0;
+        Some(state)
+    }
+}
+
+/// XORs `old` against `new` and run-length encodes the zero bytes
+/// [old length: u32][tokens...]
+fn encode_delta(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(old.len() as u32).to_le_bytes());
+
+    let xor: Vec<u8> = old
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ new.get(i).copied().unwrap_or(0))
+        .collect();
+
+    let mut i = 0;
+    while i < xor.len() {
+        let run = xor[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&byte| byte == 0)
+            .count();
+
+        if run > 0 {
+            out.push(ZERO_RUN | (run - 1) as u8);
+            i += run;
+        } else {
+            let literal = xor[i..]
+                .iter()
+                .take(MAX_RUN)
+                .take_while(|&&byte| byte != 0)
+                .count();
+
+            out.push((literal - 1) as u8);
+            out.extend_from_slice(&xor[i..i + literal]);
+            i += literal;
+        }
+    }
+
+    out
+}
+
+/// Reconstructs the older state from a delta and its successor
+fn decode_delta(delta: &[u8], new: &[u8]) -> Vec<u8> {
+    let len = u32::from_le_bytes(delta[..4].try_into().unwrap()) as usize;
+
+    let mut xor = Vec::with_capacity(len);
+    let mut i = 4;
+    while i < delta.len() {
+        let token = delta[i];
+        let run = (token & !ZERO_RUN) as usize + 1;
+        i += 1;
+
+        if token & ZERO_RUN != 0 {
+            xor.resize(xor.len() + run, 0);
+        } else {
+            xor.extend_from_slice(&delta[i..i + run]);
+            i += run;
+        }
+    }
+
+    xor.iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ new.get(i).copied().unwrap_or(0))
+        .collect()
+}
+
+impl Emulator {
+    /// Sets how often snapshots are taken and how much memory they may use
+    pub fn configure_rewind(&mut self, interval: usize, budget: usize) {
+        self.rewind = RewindBuffer::new(interval, budget);
+    }
+
+    /// Called after every frame that runs forward
+    pub fn record_rewind(&mut self) {
+        if self.rewind.tick() {
+            let state = self.snapshot();
+            self.rewind.push(state);
+        }
+    }
+
+    /// Goes back to the previous snapshot, returns false if there is nothing to rewind to
+    pub fn rewind_step(&mut self) -> bool {
+        match self.rewind.step_back() {
+            Some(state) => self.restore(&state).is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_delta, encode_delta, RewindBuffer};
+
+    #[test]
+    fn delta_round_trip() {
+        let old: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+        let mut new = old.clone();
+        // a long literal run, a long zero run and a changed last byte
+        new[10..400].iter_mut().for_each(|byte| *byte ^= 0x5A);
+        new[999] ^= 1;
+
+        assert_eq!(decode_delta(&encode_delta(&old, &new), &new), old);
+    }
+
+    #[test]
+    fn delta_between_states_of_different_sizes() {
+        let old = vec![1, 2, 3, 4, 5];
+        let new = vec![1, 2, 3];
+
+        assert_eq!(decode_delta(&encode_delta(&old, &new), &new), old);
+        assert_eq!(decode_delta(&encode_delta(&new, &old), &old), new);
+    }
+
+    #[test]
+    fn identical_states_compress_to_zero_runs() {
+        let state = vec![0xAB; 1024];
+
+        let delta = encode_delta(&state, &state);
+
+        // length and one token per 128 bytes
+        assert_eq!(delta.len(), 4 + 1024 / 128);
+        assert_eq!(decode_delta(&delta, &state), state);
+    }
+
+    #[test]
+    fn steps_back_through_the_snapshots() {
+        let mut buffer = RewindBuffer::new(1, usize::MAX);
+        for frame in 0..3 {
+            buffer.push(vec![frame; 16]);
+        }
+
+        assert_eq!(buffer.step_back(), Some(vec![2; 16]));
+        assert_eq!(buffer.step_back(), Some(vec![1; 16]));
+        assert_eq!(buffer.step_back(), Some(vec![0; 16]));
+        // the oldest snapshot stays
+        assert_eq!(buffer.step_back(), Some(vec![0; 16]));
+    }
+
+    #[test]
+    fn forgets_the_oldest_snapshots_over_budget() {
+        // the latest state and two deltas of 4 + 1 + 32 bytes
+        let mut buffer = RewindBuffer::new(1, 32 + 2 * 37);
+        for frame in 0..10 {
+            buffer.push(vec![frame; 32]);
+        }
+
+        assert_eq!(buffer.deltas.len(), 2);
+        assert_eq!(buffer.step_back(), Some(vec![9; 32]));
+        assert_eq!(buffer.step_back(), Some(vec![8; 32]));
+        assert_eq!(buffer.step_back(), Some(vec![7; 32]));
+        assert_eq!(buffer.step_back(), Some(vec![7; 32]));
+    }
+}
@@ -0,0 +1,228 @@
+use std::{fmt, fs, io, path::PathBuf};
+
+use crate::state::{StateError, StateReader, StateWriter};
+
+use super::{Emulator, StatusRegister};
+
+const STATE_MAGIC: [u8; 4] = *b"BRNS";
+
+/// Incremented whenever the layout of the state changes
+const STATE_VERSION: u32 = 11;
+
+/// Number of save state slots
+pub const STATE_SLOTS: u8 = 10;
+
+#[derive(Debug)]
+pub enum SlotError {
+    /// The slot file couldn't be read or written
+    Io { path: PathBuf, err: io::Error },
+
+    /// The slot file doesn't hold a state this ROM can load
+    State(StateError),
+}
+
+impl fmt::Display for SlotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlotError::Io { path, err } => write!(f, "{}: {}", path.display(), err),
+            SlotError::State(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Emulator {
+    fn write_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&STATE_MAGIC);
+        writer.write_u32(STATE_VERSION);
+        writer.write_u32(self.rom_crc);
+
+        writer.write_u8(self.regs.a);
+        writer.write_u8(self.regs.x);
+        writer.write_u8(self.regs.y);
+        writer.write_u8(self.regs.sp);
+        writer.write_u16(self.regs.pc);
+        writer.write_u8(self.regs.flags.bytes[0]);
+
+        writer.write_bytes(&self.internal_ram);
+        writer.write_usize(self.cycle_counter);
+        self.cpu.save_state(writer);
+        self.ppu.save_state(writer);
+        self.input.save_state(writer);
+        if let Some(player) = &self.nsf {
+            player.save_state(writer);
+        }
+
+        let mut mapper_writer = StateWriter::new();
+        self.mapper.save_state(&mut mapper_writer);
+        writer.write_blob(&mapper_writer.into_bytes());
+    }
+
+    fn read_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        if reader.read_bytes(STATE_MAGIC.len())? != STATE_MAGIC {
+            return Err(StateError::InvalidFormat);
+        }
+
+        let version = reader.read_u32()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let rom_crc = reader.read_u32()?;
+        if rom_crc != self.rom_crc {
+            return Err(StateError::RomMismatch {
+                expected: self.rom_crc,
+                actual: rom_crc,
+            });
+        }
+
+        self.regs.a = reader.read_u8()?;
+        self.regs.x = reader.read_u8()?;
+        self.regs.y = reader.read_u8()?;
+        self.regs.sp = reader.read_u8()?;
+        self.regs.pc = reader.read_u16()?;
+        self.regs.flags = StatusRegister::from_bytes([reader.read_u8()?]);
+
+        reader.read_into(&mut self.internal_ram)?;
+        self.cycle_counter = reader.read_usize()?;
+        self.cpu.load_state(reader)?;
+        self.ppu.load_state(reader)?;
+        self.input.load_state(reader)?;
+        if let Some(player) = &mut self.nsf {
+            player.load_state(reader)?;
+        }
+
+        let mapper_state = reader.read_blob()?;
+        self.mapper.load_state(&mut StateReader::new(mapper_state))
+    }
+
+    /// Captures the state of the whole machine
+    pub fn snapshot(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        self.write_state(&mut writer);
+        writer.into_bytes()
+    }
+
+    /// Restores a state captured by `snapshot`, the machine is left untouched on failure
+    pub fn restore(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let backup = self.snapshot();
+
+        let res = self.read_state(&mut StateReader::new(data));
+        if res.is_err() && self.read_state(&mut StateReader::new(&backup)).is_err() {
+            // the backup was written just above, a half restored machine is never left running
+            eprintln!("Could not roll back a failed state load, power cycling");
+            self.power_cycle();
+        }
+
+        // the state can be on another track
+        self.update_nsf_overlay();
+
+        res
+    }
+
+    /// Sets the path save state slots are stored next to(game.nes -> game.ss0-game.ss9)
+    pub fn set_state_path(&mut self, path: PathBuf) {
+        self.state_path = path;
+    }
+
+    pub fn select_state_slot(&mut self, slot: u8) {
+        assert!(slot < STATE_SLOTS);
+        self.state_slot = slot;
+    }
+
+    fn state_slot_path(&self) -> PathBuf {
+        self.state_path
+            .with_extension(format!("ss{}", self.state_slot))
+    }
+
+    pub fn save_state_slot(&mut self) -> Result<(), SlotError> {
+        let path = self.state_slot_path();
+        fs::write(&path, self.snapshot()).map_err(|err| SlotError::Io { path, err })
+    }
+
+    pub fn load_state_slot(&mut self) -> Result<(), SlotError> {
+        let path = self.state_slot_path();
+        let data = fs::read(&path).map_err(|err| SlotError::Io { path, err })?;
+
+        self.restore(&data).map_err(SlotError::State)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{emu::Emulator, state::StateError};
+
+    use super::SlotError;
+
+    #[test]
+    fn round_trip() {
+        let mut emu = Emulator::with_test_bus();
+        emu.regs.a = 0x12;
+        emu.regs.pc = 0xC123;
+        emu.internal_ram[0x42] = 0x99;
+        let state = emu.snapshot();
+
+        emu.regs.a = 0;
+        emu.regs.pc = 0;
+        emu.internal_ram[0x42] = 0;
+        emu.restore(&state).unwrap();
+
+        assert_eq!(emu.regs.a, 0x12);
+        assert_eq!(emu.regs.pc, 0xC123);
+        assert_eq!(emu.internal_ram[0x42], 0x99);
+        assert_eq!(emu.snapshot(), state);
+    }
+
+    #[test]
+    fn failed_restore_leaves_the_machine_untouched() {
+        let mut emu = Emulator::with_test_bus();
+        emu.regs.x = 0x34;
+        let mut state = emu.snapshot();
+
+        emu.regs.x = 0x56;
+        emu.internal_ram[0] = 0x78;
+        let before = emu.snapshot();
+
+        state.truncate(state.len() - 1);
+        assert!(matches!(emu.restore(&state), Err(StateError::Corrupted)));
+        assert_eq!(emu.snapshot(), before);
+
+        assert!(matches!(
+            emu.restore(b"NOPE"),
+            Err(StateError::InvalidFormat)
+        ));
+        assert_eq!(emu.snapshot(), before);
+    }
+
+    #[test]
+    fn rejects_a_state_of_another_rom() {
+        let mut emu = Emulator::with_test_bus();
+        let state = emu.snapshot();
+
+        emu.rom_crc ^= 1;
+
+        assert!(matches!(
+            emu.restore(&state),
+            Err(StateError::RomMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn slot_round_trip() {
+        let dir = std::env::temp_dir().join(format!("baroness-slot-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut emu = Emulator::with_test_bus();
+        emu.set_state_path(dir.join("game.nes"));
+        emu.select_state_slot(3);
+        assert!(matches!(emu.load_state_slot(), Err(SlotError::Io { .. })));
+
+        emu.regs.y = 0x9A;
+        emu.save_state_slot().unwrap();
+        emu.regs.y = 0;
+        let res = emu.load_state_slot();
+
+        std::fs::remove_dir_all(&dir).ok();
+        res.unwrap();
+        assert_eq!(emu.regs.y, 0x9A);
+    }
+}
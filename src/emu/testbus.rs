@@ -0,0 +1,92 @@
+use crate::nes::{MirroringMode, NESFile, PRG_ROM_UNIT};
+
+use super::Emulator;
+
+/// A single bus cycle
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BusAccess {
+    pub addr: u16,
+    pub val: u8,
+    pub write: bool,
+}
+
+/// Flat 64 KiB of RAM replacing the whole address space, used to test the CPU in isolation
+pub struct TestBus {
+    memory: Box<[u8]>,
+    /// Accesses made since the log was last cleared
+    pub log: Vec<BusAccess>,
+    /// Addresses written or poked since the bus was last cleared
+    dirty: Vec<u16>,
+}
+
+impl TestBus {
+    pub fn read(&mut self, addr: u16) -> u8 {
+        let val = self.memory[addr as usize];
+        self.log.push(BusAccess {
+            addr,
+            val,
+            write: false,
+        });
+        val
+    }
+
+    pub fn write(&mut self, addr: u16, val: u8) {
+        self.memory[addr as usize] = val;
+        self.dirty.push(addr);
+        self.log.push(BusAccess {
+            addr,
+            val,
+            write: true,
+        });
+    }
+}
+
+impl Emulator {
+    /// Creates an emulator whose CPU sees nothing but the test bus
+    pub fn with_test_bus() -> Emulator {
+        let nes_file = NESFile {
+            mirroring_mode: MirroringMode::Horizontal,
+            has_prg_ram: false,
+            has_trainer: false,
+            mapper_number: 0,
+            prg_rom: vec![0; PRG_ROM_UNIT],
+            chr_rom: Vec::new(),
+            disk_sides: Vec::new(),
+            nsf: None,
+            region: None,
+        };
+
+        let mut emu = Emulator::new(nes_file).expect("NROM is always supported");
+        emu.test_bus = Some(TestBus {
+            memory: vec![0; 0x10000].into_boxed_slice(),
+            log: Vec::new(),
+            dirty: Vec::new(),
+        });
+        emu
+    }
+
+    pub fn test_bus(&mut self) -> &mut TestBus {
+        self.test_bus.as_mut().expect("no test bus")
+    }
+
+    /// Zeroes every byte written or poked since the last clear, which leaves the whole address
+    /// space zeroed without touching all 64 KiB for each test case
+    pub fn clear_test_bus(&mut self) {
+        let bus = self.test_bus();
+        for addr in bus.dirty.drain(..) {
+            bus.memory[addr as usize] = 0;
+        }
+    }
+
+    /// Sets a byte without logging the access
+    pub fn poke_test_bus(&mut self, addr: u16, val: u8) {
+        let bus = self.test_bus();
+        bus.memory[addr as usize] = val;
+        bus.dirty.push(addr);
+    }
+
+    /// Reads a byte without logging the access
+    pub fn peek_test_bus(&mut self, addr: u16) -> u8 {
+        self.test_bus().memory[addr as usize]
+    }
+}
@@ -0,0 +1,68 @@
+use crate::inst::INSTRUCTIONS;
+
+use super::Emulator;
+
+/// Flags bit that only exists in the copies pushed on the stack
+const BREAK_FLAG: u8 = 0x10;
+
+/// Flags bit that always reads as set
+const UNUSED_FLAG: u8 = 0x20;
+
+impl Emulator {
+    /// Records a trace line in the nestest.log format for the instruction at PC
+    /// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+    pub fn trace_instruction(&mut self) {
+        let opcode = self.peek(self.regs.pc);
+        let ins = &INSTRUCTIONS[opcode as usize];
+        let operand = self.peek_operand(self.regs.pc, ins.addressing_mode);
+
+        let bytes: Vec<String> = (0..ins.bytes as u16)
+            .map(|off| format!("{:02X}", self.peek(self.regs.pc.wrapping_add(off))))
+            .collect();
+
+        let (scanline, dot) = self.ppu_position();
+
+        let line = format!(
+            "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            self.regs.pc,
+            bytes.join(" "),
+            if ins.unofficial { '*' } else { ' ' },
+            self.format_instruction(self.regs.pc, ins, operand),
+            self.regs.a,
+            self.regs.x,
+            self.regs.y,
+            self.regs.flags.bytes[0] & !BREAK_FLAG | UNUSED_FLAG,
+            self.regs.sp,
+            scanline,
+            dot,
+            self.cpu.cycles
+        );
+
+        if let Some(trace) = &mut self.cpu.trace {
+            trace.push(line);
+        }
+    }
+
+    /// Starts collecting trace lines
+    pub fn enable_trace(&mut self) {
+        self.cpu.trace = Some(Vec::new());
+    }
+
+    /// Runs until the next instruction executes and returns its trace line
+    pub fn next_trace_line(&mut self) -> String {
+        loop {
+            if let Some(trace) = &mut self.cpu.trace {
+                if !trace.is_empty() {
+                    return trace.remove(0);
+                }
+            }
+
+            self.step_cpu();
+        }
+    }
+
+    /// Starts executing at the mapper entrypoint instead of the reset vector
+    pub fn jump_to_entrypoint(&mut self) {
+        self.regs.pc = self.mapper.entrypoint();
+    }
+}
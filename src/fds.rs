@@ -0,0 +1,140 @@
+use crate::{
+    nes::{MirroringMode, NESFile},
+    region::Region,
+};
+
+/// fwNES header magic
+const FDS_MAGIC: [u8; 4] = *b"FDS\x1A";
+
+/// Size of the fwNES header
+const HEADER_SIZE: usize = 16;
+
+/// Every disk side starts with the disk info block
+const DISK_MAGIC: &[u8] = b"\x01*NINTENDO-HVC*";
+
+/// Size of a side in .fds images
+pub const SIDE_SIZE: usize = 65500;
+
+/// Size of a side in QD images, which also contain the block CRCs
+const QD_SIDE_SIZE: usize = 65536;
+
+/// Size of disksys.rom
+const BIOS_SIZE: usize = usize::pow(2, 13);
+
+/// Mapper number reserved for the FDS
+pub const FDS_MAPPER_NUMBER: u8 = 20;
+
+const DISK_INFO_BLOCK: u8 = 1;
+const FILE_AMOUNT_BLOCK: u8 = 2;
+const FILE_HEADER_BLOCK: u8 = 3;
+const FILE_DATA_BLOCK: u8 = 4;
+
+const DISK_INFO_BLOCK_SIZE: usize = 56;
+const FILE_AMOUNT_BLOCK_SIZE: usize = 2;
+const FILE_HEADER_BLOCK_SIZE: usize = 16;
+
+/// Returns whether the file is a .fds or QD disk image
+pub fn is_fds_file(file: &[u8]) -> bool {
+    file.starts_with(&FDS_MAGIC) || file.starts_with(DISK_MAGIC)
+}
+
+/// Reads the block with the given ID at `off` and advances past it and its CRC
+fn next_block<'a>(
+    side: &'a [u8],
+    id: u8,
+    size: usize,
+    crc_size: usize,
+    off: &mut usize,
+) -> Option<&'a [u8]> {
+    if side.get(*off) != Some(&id) {
+        return None;
+    }
+
+    let block = side.get(*off..*off + size)?;
+    *off += size + crc_size;
+    Some(block)
+}
+
+/// Splits a disk side into its blocks, `crc_size` is the number of CRC bytes following each block
+pub fn disk_blocks(side: &[u8], crc_size: usize) -> Vec<&[u8]> {
+    let mut blocks = Vec::new();
+    let mut off = 0;
+
+    let header_blocks = [
+        (DISK_INFO_BLOCK, DISK_INFO_BLOCK_SIZE),
+        (FILE_AMOUNT_BLOCK, FILE_AMOUNT_BLOCK_SIZE),
+    ];
+
+    for (id, size) in header_blocks {
+        match next_block(side, id, size, crc_size, &mut off) {
+            Some(block) => blocks.push(block),
+            None => return blocks,
+        }
+    }
+
+    // the file amount isn't trusted, some games store files after the ones they declare
+    while let Some(header) = next_block(
+        side,
+        FILE_HEADER_BLOCK,
+        FILE_HEADER_BLOCK_SIZE,
+        crc_size,
+        &mut off,
+    ) {
+        blocks.push(header);
+
+        let file_size = u16::from_le_bytes([header[13], header[14]]) as usize;
+        match next_block(side, FILE_DATA_BLOCK, file_size + 1, crc_size, &mut off) {
+            Some(data) => blocks.push(data),
+            None => break,
+        }
+    }
+
+    blocks
+}
+
+// https://www.nesdev.org/wiki/FDS_disk_format
+pub fn parse_fds_file(file: &[u8], bios: &[u8]) -> Result<NESFile, ()> {
+    if !is_fds_file(file) || bios.len() != BIOS_SIZE {
+        return Err(());
+    }
+
+    let data = if file.starts_with(&FDS_MAGIC) {
+        file.get(HEADER_SIZE..).ok_or(())?
+    } else {
+        file
+    };
+
+    let is_qd = data.len() % SIDE_SIZE != 0 && data.len() % QD_SIDE_SIZE == 0;
+
+    let disk_sides: Vec<Vec<u8>> = if is_qd {
+        // strip the CRCs so every side is stored in the .fds layout
+        data.chunks_exact(QD_SIDE_SIZE)
+            .map(|side| {
+                let mut fds_side = disk_blocks(side, 2).concat();
+                fds_side.resize(SIDE_SIZE, 0);
+                fds_side
+            })
+            .collect()
+    } else {
+        data.chunks_exact(SIDE_SIZE).map(Vec::from).collect()
+    };
+
+    if disk_sides.is_empty() {
+        return Err(());
+    }
+
+    let nes = NESFile {
+        mirroring_mode: MirroringMode::Horizontal,
+        has_prg_ram: true,
+        has_trainer: false,
+        mapper_number: FDS_MAPPER_NUMBER,
+        prg_rom: Vec::from(bios),
+        chr_rom: Vec::new(),
+        disk_sides,
+        nsf: None,
+        // the FDS was only released in Japan
+        region: Some(Region::Ntsc),
+    };
+
+    Ok(nes)
+}
@@ -34,21 +34,17 @@ fn do_cmp(emu: &mut Emulator, val: u8) {
     emu.regs.flags.set_zero((val == 0).into());
 }
 
-pub fn adc(emu: &mut Emulator, op: Operand) -> usize {
+pub fn adc(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op);
     do_adc(emu, val);
-
-    0
 }
 
-pub fn cmp(emu: &mut Emulator, op: Operand) -> usize {
+pub fn cmp(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op);
     do_cmp(emu, val);
-
-    0
 }
 
-pub fn cpx(emu: &mut Emulator, op: Operand) -> usize {
+pub fn cpx(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op) as i8;
     let reg = emu.regs.x as i8;
 
@@ -57,11 +53,9 @@ pub fn cpx(emu: &mut Emulator, op: Operand) -> usize {
     emu.regs.flags.set_carry((reg as u8 >= val as u8).into());
     emu.regs.flags.set_zero((res == 0).into());
     emu.regs.flags.set_negative((res < 0).into());
-
-    0
 }
 
-pub fn cpy(emu: &mut Emulator, op: Operand) -> usize {
+pub fn cpy(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op) as i8;
     let reg = emu.regs.y as i8;
 
@@ -70,75 +64,59 @@ pub fn cpy(emu: &mut Emulator, op: Operand) -> usize {
     emu.regs.flags.set_carry((reg as u8 >= val as u8).into());
     emu.regs.flags.set_zero((res == 0).into());
     emu.regs.flags.set_negative((res < 0).into());
-
-    0
 }
 
-pub fn sbc(emu: &mut Emulator, op: Operand) -> usize {
+pub fn sbc(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op);
     do_sbc(emu, val);
-
-    0
 }
 
-pub fn dec(emu: &mut Emulator, op: Operand) -> usize {
+pub fn dec(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
+    emu.read_modify_write(addr, |emu, val| {
+        let new_val = val.wrapping_sub(1);
+        emu.set_zero_and_negative_flags(new_val);
 
-    let val = emu.read(addr) as i8;
-    let new_val = val.wrapping_sub(1) as u8;
-
-    emu.set_zero_and_negative_flags(new_val);
-    emu.write(addr, new_val);
-
-    0
+        new_val
+    });
 }
 
-pub fn dex(emu: &mut Emulator, op: Operand) -> usize {
+pub fn dex(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => emu.set_x(emu.regs.x.wrapping_sub(1)),
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn dey(emu: &mut Emulator, op: Operand) -> usize {
+pub fn dey(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => emu.set_y(emu.regs.y.wrapping_sub(1)),
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn inc(emu: &mut Emulator, op: Operand) -> usize {
+pub fn inc(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
+    emu.read_modify_write(addr, |emu, val| {
+        let new_val = val.wrapping_add(1);
+        emu.set_zero_and_negative_flags(new_val);
 
-    let val = emu.read(addr) as i8;
-    let new_val = val.wrapping_add(1) as u8;
-
-    emu.set_zero_and_negative_flags(new_val);
-    emu.write(addr, new_val);
-
-    0
+        new_val
+    });
 }
 
-pub fn inx(emu: &mut Emulator, op: Operand) -> usize {
+pub fn inx(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => emu.set_x(emu.regs.x.wrapping_add(1)),
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn iny(emu: &mut Emulator, op: Operand) -> usize {
+pub fn iny(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => emu.set_y(emu.regs.y.wrapping_add(1)),
         _ => unreachable!(),
     }
-
-    0
 }
 
 //
@@ -147,24 +125,30 @@ pub fn iny(emu: &mut Emulator, op: Operand) -> usize {
 //
 //
 
-pub fn dcp(emu: &mut Emulator, op: Operand) -> usize {
+pub fn dcp(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
-    let val = emu.read(addr);
+    emu.read_modify_write(addr, |emu, val| {
+        let subbed = val.wrapping_sub(1);
+        do_cmp(emu, subbed);
 
-    let subbed = (val as i8).wrapping_sub(1);
-    do_cmp(emu, subbed as u8);
-    emu.write(addr, subbed as u8);
-
-    0
+        subbed
+    });
 }
 
-pub fn isc(emu: &mut Emulator, op: Operand) -> usize {
+pub fn isc(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
-    let val = emu.read(addr);
+    emu.read_modify_write(addr, |emu, val| {
+        let added = val.wrapping_add(1);
+        do_sbc(emu, added);
+
+        added
+    });
+}
 
-    let added = (val as i8).wrapping_add(1);
-    do_sbc(emu, added as u8);
-    emu.write(addr, added as u8);
+pub fn axs(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op);
+    let reg = emu.regs.a & emu.regs.x;
 
-    0
+    emu.regs.flags.set_carry((reg >= val).into());
+    emu.set_x(reg.wrapping_sub(val));
 }
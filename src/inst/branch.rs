@@ -4,18 +4,18 @@ use super::Operand;
 
 macro_rules! branch_fn {
     ($name: ident, $flag: ident, $cond: expr) => {
-        pub fn $name(emu: &mut Emulator, op: Operand) -> usize {
-            let mut extra_cycles = 0;
-
+        pub fn $name(emu: &mut Emulator, op: Operand) {
             match op {
                 Operand::Relative(off) => {
                     let final_addr = emu.regs.pc.wrapping_add_signed(off as i8 as i16);
 
                     if emu.regs.flags.$flag() == $cond {
-                        extra_cycles += 1;
+                        // the next opcode is read while the offset is added
+                        emu.read(emu.regs.pc);
 
                         if final_addr & 0xFF00 != emu.regs.pc & 0xFF00 {
-                            extra_cycles += 1;
+                            // and again before the high byte is fixed
+                            emu.read(emu.regs.pc & 0xFF00 | final_addr & 0x00FF);
                         }
 
                         emu.regs.pc = final_addr;
@@ -23,8 +23,6 @@ macro_rules! branch_fn {
                 }
                 _ => unreachable!(),
             }
-
-            extra_cycles
         }
     };
 }
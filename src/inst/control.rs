@@ -1,34 +1,19 @@
-use crate::emu::{Emulator, StatusRegister};
+use crate::emu::Emulator;
 
 use super::Operand;
 
-pub fn brk(emu: &mut Emulator, op: Operand) -> usize {
+pub fn brk(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
-            emu.push_on_stack(emu.regs.flags.clone().into_bytes()[0]);
-
-            let (ret_high, ret_low) = {
-                let ret = emu.regs.pc;
-                ((ret >> 8) as u8, (ret & 0xFF) as u8)
-            };
-
-            emu.push_on_stack(ret_low);
-            emu.push_on_stack(ret_high);
-
-            let addr_high = emu.read(0xFFFE) as u16;
-            let addr_low = emu.read(0xFFFF) as u16;
-
-            let addr = addr_high << 8 | addr_low;
-
-            emu.regs.pc = addr;
+            // the byte after the opcode is skipped as padding
+            emu.regs.pc = emu.regs.pc.wrapping_add(1);
+            emu.interrupt(true);
         }
         _ => unreachable!(),
     };
-
-    0
 }
 
-pub fn jmp(emu: &mut Emulator, op: Operand) -> usize {
+pub fn jmp(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Absolute(addr) => {
             emu.regs.pc = addr;
@@ -39,32 +24,30 @@ pub fn jmp(emu: &mut Emulator, op: Operand) -> usize {
         }
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn jsr(emu: &mut Emulator, op: Operand) -> usize {
+/// Gets only the low byte of the address, the high byte is read after the return address is pushed
+pub fn jsr(emu: &mut Emulator, op: Operand) {
     match op {
-        Operand::Absolute(addr) => {
-            let (ret_high, ret_low) = {
-                let ret = emu.regs.pc - 1;
-                ((ret >> 8) as u8, (ret & 0xFF) as u8)
-            };
+        Operand::Immediate(addr_low) => {
+            emu.dummy_stack_read();
 
+            let [ret_low, ret_high] = emu.regs.pc.to_le_bytes();
             emu.push_on_stack(ret_high);
             emu.push_on_stack(ret_low);
 
-            emu.regs.pc = addr;
+            let addr_high = emu.read(emu.regs.pc);
+            emu.regs.pc = u16::from_le_bytes([addr_low, addr_high]);
         }
         _ => unreachable!(),
     };
-
-    0
 }
 
-pub fn rti(emu: &mut Emulator, op: Operand) -> usize {
+pub fn rti(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
+            emu.dummy_stack_read();
+
             let status = emu.pop_stack();
 
             let ret_low = emu.pop_stack() as u16;
@@ -72,27 +55,34 @@ pub fn rti(emu: &mut Emulator, op: Operand) -> usize {
 
             let ret_addr = ret_high << 8 | ret_low;
 
-            emu.regs.flags = StatusRegister::from_bytes([status]);
+            emu.set_pulled_flags(status);
             emu.regs.pc = ret_addr;
         }
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn rts(emu: &mut Emulator, op: Operand) -> usize {
+pub fn rts(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
+            emu.dummy_stack_read();
+
             let ret_low = emu.pop_stack() as u16;
             let ret_high = emu.pop_stack() as u16;
 
             let ret_addr = ret_high << 8 | ret_low;
 
-            emu.regs.pc = ret_addr + 1;
+            // the CPU reads the return address before incrementing it
+            emu.read(ret_addr);
+            emu.regs.pc = ret_addr.wrapping_add(1);
         }
         _ => unreachable!(),
     }
+}
 
-    0
+pub fn kil(emu: &mut Emulator, op: Operand) {
+    match op {
+        Operand::Implied => emu.jam(),
+        _ => unreachable!(),
+    }
 }
@@ -2,15 +2,13 @@ use super::{Emulator, Operand};
 
 macro_rules! flags_fn {
     ($name: ident, $flag: ident, $cond: expr) => {
-        pub fn $name(emu: &mut Emulator, op: Operand) -> usize {
+        pub fn $name(emu: &mut Emulator, op: Operand) {
             match op {
                 Operand::Implied => {
                     emu.regs.flags.$flag($cond);
                 }
                 _ => unreachable!(),
             }
-
-            0
         }
     };
 }
@@ -1,59 +1,83 @@
 use super::{Emulator, Operand};
 
-pub fn lda(emu: &mut Emulator, op: Operand) -> usize {
-    let (val, crossed) = emu.get_val_from_operand_cross(op);
+pub fn lda(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op);
     emu.set_a(val);
-    crossed.into()
 }
 
-pub fn ldx(emu: &mut Emulator, op: Operand) -> usize {
-    let (val, crossed) = emu.get_val_from_operand_cross(op);
+pub fn ldx(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op);
     emu.set_x(val);
-    crossed.into()
 }
 
-pub fn ldy(emu: &mut Emulator, op: Operand) -> usize {
-    let (val, crossed) = emu.get_val_from_operand_cross(op);
+pub fn ldy(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op);
     emu.set_y(val);
-    crossed.into()
 }
 
-pub fn sta(emu: &mut Emulator, op: Operand) -> usize {
+pub fn sta(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
     emu.write(addr, emu.regs.a);
-    0
 }
 
-pub fn stx(emu: &mut Emulator, op: Operand) -> usize {
+pub fn stx(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
     emu.write(addr, emu.regs.x);
-    0
 }
 
-pub fn sty(emu: &mut Emulator, op: Operand) -> usize {
+pub fn sty(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
     emu.write(addr, emu.regs.y);
-    0
 }
 
 // Unofficial
 
-pub fn lax(emu: &mut Emulator, op: Operand) -> usize {
-    let (val, crossed) = emu.get_val_from_operand_cross(op);
+pub fn lax(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op);
 
     emu.regs.a = val;
     emu.regs.x = val;
 
     emu.regs.flags.set_zero((val == 0).into());
     emu.regs.flags.set_negative((val & (1 << 7) > 0).into());
-
-    crossed.into()
 }
 
-pub fn sax(emu: &mut Emulator, op: Operand) -> usize {
+pub fn sax(emu: &mut Emulator, op: Operand) {
     let res = emu.regs.a & emu.regs.x;
 
     let addr = emu.get_addr_from_operand(op);
     emu.write(addr, res);
-    0
+}
+
+/// LAX with A ORed with a chip dependent constant first
+pub fn lxa(emu: &mut Emulator, op: Operand) {
+    let val = (emu.regs.a | emu.unstable_magic()) & emu.get_val_from_operand(op);
+
+    emu.set_a(val);
+    emu.set_x(val);
+}
+
+pub fn las(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op) & emu.regs.sp;
+
+    emu.regs.sp = val;
+    emu.set_a(val);
+    emu.set_x(val);
+}
+
+pub fn sha(emu: &mut Emulator, op: Operand) {
+    emu.write_unstable(op, emu.regs.a & emu.regs.x);
+}
+
+pub fn shx(emu: &mut Emulator, op: Operand) {
+    emu.write_unstable(op, emu.regs.x);
+}
+
+pub fn shy(emu: &mut Emulator, op: Operand) {
+    emu.write_unstable(op, emu.regs.y);
+}
+
+pub fn tas(emu: &mut Emulator, op: Operand) {
+    emu.regs.sp = emu.regs.a & emu.regs.x;
+    emu.write_unstable(op, emu.regs.sp);
 }
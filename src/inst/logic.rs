@@ -37,102 +37,76 @@ fn get_logical_operand(emu: &mut Emulator, op: Operand) -> Option<u16> {
     }
 }
 
-pub fn asl(emu: &mut Emulator, op: Operand) -> usize {
+pub fn asl(emu: &mut Emulator, op: Operand) {
     let addr = get_logical_operand(emu, op);
 
-    let (new_val, carry, negative) = if let Some(addr) = addr {
-        let val = emu.read(addr);
-
+    let shift = |emu: &mut Emulator, val: u8| {
         let new_val = val << 1;
-        emu.write(addr, new_val);
-
-        (new_val, val & (1 << 7) > 0, new_val & (1 << 7) > 0)
-    } else {
-        let val = emu.regs.a;
 
-        let new_val = val << 1;
-        emu.regs.a = new_val;
+        emu.regs.flags.set_carry((val & (1 << 7) > 0).into());
+        emu.regs.flags.set_negative((new_val & (1 << 7) > 0).into());
+        emu.regs.flags.set_zero((new_val == 0).into());
 
-        (new_val, val & (1 << 7) > 0, new_val & (1 << 7) > 0)
+        new_val
     };
 
-    emu.regs.flags.set_carry(carry.into());
-    emu.regs.flags.set_negative(negative.into());
-    emu.regs.flags.set_zero((new_val == 0).into());
-
-    0
+    if let Some(addr) = addr {
+        emu.read_modify_write(addr, shift);
+    } else {
+        emu.regs.a = shift(emu, emu.regs.a);
+    }
 }
 
-pub fn lsr(emu: &mut Emulator, op: Operand) -> usize {
+pub fn lsr(emu: &mut Emulator, op: Operand) {
     let addr = get_logical_operand(emu, op);
 
-    let (new_val, carry) = if let Some(addr) = addr {
-        let val = emu.read(addr);
-
+    let shift = |emu: &mut Emulator, val: u8| {
         let new_val = val >> 1;
-        emu.write(addr, new_val);
-
-        (new_val, val & (1 << 0) > 0)
-    } else {
-        let val = emu.regs.a;
 
-        let new_val = val >> 1;
-        emu.regs.a = new_val;
+        emu.regs.flags.set_carry((val & (1 << 0) > 0).into());
+        emu.regs.flags.set_negative(0);
+        emu.regs.flags.set_zero((new_val == 0).into());
 
-        (new_val, val & (1 << 0) > 0)
+        new_val
     };
 
-    emu.regs.flags.set_carry(carry.into());
-    emu.regs.flags.set_negative(0);
-    emu.regs.flags.set_zero((new_val == 0).into());
-
-    0
+    if let Some(addr) = addr {
+        emu.read_modify_write(addr, shift);
+    } else {
+        emu.regs.a = shift(emu, emu.regs.a);
+    }
 }
 
-pub fn rol(emu: &mut Emulator, op: Operand) -> usize {
+pub fn rol(emu: &mut Emulator, op: Operand) {
     match get_logical_operand(emu, op) {
-        Some(addr) => {
-            let val = emu.read(addr);
-            let rotated = rotate_left(emu, val, true);
-            emu.write(addr, rotated)
-        }
+        Some(addr) => emu.read_modify_write(addr, |emu, val| rotate_left(emu, val, true)),
         None => {
             let val = emu.regs.a;
             let rotated = rotate_left(emu, val, true);
             emu.regs.a = rotated;
         }
     };
-
-    0
 }
 
-pub fn ror(emu: &mut Emulator, op: Operand) -> usize {
+pub fn ror(emu: &mut Emulator, op: Operand) {
     match get_logical_operand(emu, op) {
-        Some(addr) => {
-            let val = emu.read(addr);
-            let rotated = rotate_right(emu, val, true);
-            emu.write(addr, rotated)
-        }
+        Some(addr) => emu.read_modify_write(addr, |emu, val| rotate_right(emu, val, true)),
         None => {
             let val = emu.regs.a;
             let rotated = rotate_right(emu, val, true);
             emu.regs.a = rotated;
         }
     };
-
-    0
 }
 
-pub fn and(emu: &mut Emulator, op: Operand) -> usize {
+pub fn and(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op);
 
     let new_val = val & emu.regs.a;
     emu.set_a(new_val);
-
-    0
 }
 
-pub fn bit(emu: &mut Emulator, op: Operand) -> usize {
+pub fn bit(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op);
 
     emu.regs.flags.set_negative((val & (1 << 7) != 0).into());
@@ -140,26 +114,20 @@ pub fn bit(emu: &mut Emulator, op: Operand) -> usize {
 
     let result = val & emu.regs.a;
     emu.regs.flags.set_zero((result == 0).into());
-
-    0
 }
 
-pub fn eor(emu: &mut Emulator, op: Operand) -> usize {
+pub fn eor(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op);
 
     let new_val = val ^ emu.regs.a;
     emu.set_a(new_val);
-
-    0
 }
 
-pub fn ora(emu: &mut Emulator, op: Operand) -> usize {
+pub fn ora(emu: &mut Emulator, op: Operand) {
     let val = emu.get_val_from_operand(op);
 
     let new_val = val | emu.regs.a;
     emu.set_a(new_val);
-
-    0
 }
 
 //
@@ -168,57 +136,82 @@ pub fn ora(emu: &mut Emulator, op: Operand) -> usize {
 //
 //
 
-pub fn slo(emu: &mut Emulator, op: Operand) -> usize {
+pub fn slo(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
-    let val = emu.read(addr);
-    let new_val = val << 1;
-
-    emu.regs.flags.set_carry((val & (1 << 7) > 0).into());
+    emu.read_modify_write(addr, |emu, val| {
+        let new_val = val << 1;
 
-    let res = emu.regs.a | new_val;
+        emu.regs.flags.set_carry((val & (1 << 7) > 0).into());
 
-    emu.set_a(res);
-    emu.write(addr, new_val);
+        let res = emu.regs.a | new_val;
+        emu.set_a(res);
 
-    0
+        new_val
+    });
 }
 
-pub fn sre(emu: &mut Emulator, op: Operand) -> usize {
+pub fn sre(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
-    let val = emu.read(addr);
-    let new_val = val >> 1;
+    emu.read_modify_write(addr, |emu, val| {
+        let new_val = val >> 1;
+
+        emu.regs.flags.set_carry((val & (1 << 0) > 0).into());
 
-    emu.regs.flags.set_carry((val & (1 << 0) > 0).into());
+        let res = emu.regs.a ^ new_val;
+        emu.set_a(res);
 
-    let res = emu.regs.a ^ new_val;
+        new_val
+    });
+}
 
-    emu.set_a(res);
-    emu.write(addr, new_val);
+pub fn rla(emu: &mut Emulator, op: Operand) {
+    let addr = emu.get_addr_from_operand(op);
+    emu.read_modify_write(addr, |emu, val| {
+        let new_val = rotate_left(emu, val, true);
+        let new_acc = emu.regs.a & new_val;
+        emu.set_a(new_acc);
 
-    0
+        new_val
+    });
 }
 
-pub fn rla(emu: &mut Emulator, op: Operand) -> usize {
+pub fn rra(emu: &mut Emulator, op: Operand) {
     let addr = emu.get_addr_from_operand(op);
-    let val = emu.read(addr);
+    emu.read_modify_write(addr, |emu, val| {
+        let new_val = rotate_right(emu, val, true);
+        arithmetic::do_adc(emu, new_val);
 
-    let new_val = rotate_left(emu, val, true);
-    let new_acc = emu.regs.a & new_val;
+        new_val
+    });
+}
 
-    emu.set_a(new_acc);
-    emu.write(addr, new_val);
+pub fn anc(emu: &mut Emulator, op: Operand) {
+    and(emu, op);
 
-    0
+    emu.regs.flags.set_carry(emu.regs.flags.negative());
 }
 
-pub fn rra(emu: &mut Emulator, op: Operand) -> usize {
-    let addr = emu.get_addr_from_operand(op);
-    let val = emu.read(addr);
+pub fn alr(emu: &mut Emulator, op: Operand) {
+    and(emu, op);
+    lsr(emu, Operand::Accumulator);
+}
 
-    let new_val = rotate_right(emu, val, true);
+pub fn arr(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op) & emu.regs.a;
+    let new_val = rotate_right(emu, val, false);
+    emu.set_a(new_val);
 
-    arithmetic::do_adc(emu, new_val);
-    emu.write(addr, new_val);
+    // carry and overflow come from the adder, which sees bits 6 and 5 of the result
+    let bit_6 = new_val >> 6 & 1;
+    let bit_5 = new_val >> 5 & 1;
+    emu.regs.flags.set_carry(bit_6);
+    emu.regs.flags.set_overflow(bit_6 ^ bit_5);
+}
 
-    0
+/// A is ORed with a chip dependent constant before the AND
+pub fn xaa(emu: &mut Emulator, op: Operand) {
+    let val = emu.get_val_from_operand(op);
+
+    let new_val = (emu.regs.a | emu.unstable_magic()) & emu.regs.x & val;
+    emu.set_a(new_val);
 }
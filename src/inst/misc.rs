@@ -1,11 +1,11 @@
 use super::{Emulator, Operand};
 
-pub fn nop(emu: &mut Emulator, op: Operand) -> usize {
+pub fn nop(emu: &mut Emulator, op: Operand) {
     match op {
-        Operand::AbsoluteIndexedX(addr) => {
-            let final_addr = addr.wrapping_add(emu.regs.x as u16);
-            (final_addr & 0xFF00 != addr & 0xFF00).into()
+        // the unofficial nops still read their operand
+        Operand::Accumulator | Operand::Implied | Operand::Immediate(_) => {}
+        _ => {
+            emu.get_val_from_operand(op);
         }
-        _ => 0,
     }
 }
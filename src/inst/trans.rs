@@ -1,67 +1,55 @@
 use super::{Emulator, Operand};
 
-pub fn tax(emu: &mut Emulator, op: Operand) -> usize {
+pub fn tax(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
             emu.set_x(emu.regs.a);
         }
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn tay(emu: &mut Emulator, op: Operand) -> usize {
+pub fn tay(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
             emu.set_y(emu.regs.a);
         }
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn tsx(emu: &mut Emulator, op: Operand) -> usize {
+pub fn tsx(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
             emu.set_x(emu.regs.sp);
         }
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn txa(emu: &mut Emulator, op: Operand) -> usize {
+pub fn txa(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
             emu.set_a(emu.regs.x);
         }
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn txs(emu: &mut Emulator, op: Operand) -> usize {
+pub fn txs(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
             emu.regs.sp = emu.regs.x;
         }
         _ => unreachable!(),
     }
-
-    0
 }
 
-pub fn tya(emu: &mut Emulator, op: Operand) -> usize {
+pub fn tya(emu: &mut Emulator, op: Operand) {
     match op {
         Operand::Implied => {
             emu.set_a(emu.regs.y);
         }
         _ => unreachable!(),
     }
-
-    0
 }
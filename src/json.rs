@@ -0,0 +1,197 @@
+/// Minimal JSON reader for the test suites, only what they use is supported
+pub enum Value {
+    /// true, false or null, the test suites don't rely on them
+    Literal,
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Number(num) if *num >= 0.0 && num.fract() == 0.0 => Some(*num as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.data.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ()> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &[u8]) -> Result<(), ()> {
+        if self.data[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            Ok(())
+        } else {
+            Err(())
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ()> {
+        match self.peek().ok_or(())? {
+            b'{' => self.parse_object(),
+            b'[' => self.parse_array(),
+            b'"' => Ok(Value::String(self.parse_string()?)),
+            b't' => self.expect_literal(b"true").map(|_| Value::Literal),
+            b'f' => self.expect_literal(b"false").map(|_| Value::Literal),
+            b'n' => self.expect_literal(b"null").map(|_| Value::Literal),
+            _ => self.parse_number(),
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, ()> {
+        self.expect(b'{')?;
+
+        let mut fields = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            fields.push((key, self.parse_value()?));
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                }
+                _ => return Err(()),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ()> {
+        self.expect(b'[')?;
+
+        let mut values = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(()),
+            }
+        }
+    }
+
+    /// Strings without escapes are the common case, escapes other than \uXXXX are supported
+    fn parse_string(&mut self) -> Result<String, ()> {
+        self.expect(b'"')?;
+
+        let mut string = Vec::new();
+        loop {
+            let byte = *self.data.get(self.pos).ok_or(())?;
+            self.pos += 1;
+
+            match byte {
+                b'"' => return String::from_utf8(string).map_err(|_| ()),
+                b'\\' => {
+                    let escaped = *self.data.get(self.pos).ok_or(())?;
+                    self.pos += 1;
+                    string.push(match escaped {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        b'b' => 0x08,
+                        b'f' => 0x0C,
+                        b'"' | b'\\' | b'/' => escaped,
+                        _ => return Err(()),
+                    });
+                }
+                _ => string.push(byte),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Value, ()> {
+        let start = self.pos;
+        while self.pos < self.data.len()
+            && matches!(
+                self.data[self.pos],
+                b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'
+            )
+        {
+            self.pos += 1;
+        }
+
+        std::str::from_utf8(&self.data[start..self.pos])
+            .map_err(|_| ())?
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| ())
+    }
+}
+
+pub fn parse_json(data: &[u8]) -> Result<Value, ()> {
+    let mut parser = Parser { data, pos: 0 };
+    let value = parser.parse_value()?;
+
+    if parser.peek().is_some() {
+        return Err(());
+    }
+
+    Ok(value)
+}
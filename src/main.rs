@@ -1,19 +1,309 @@
 #![feature(const_mut_refs)]
 
-use std::fs;
+use std::{fs, path::Path};
 
+mod args;
+mod blargg;
+mod checksum;
+mod cputest;
+mod crt;
 mod emu;
+mod fds;
 mod inst;
+mod json;
 mod mapper;
+mod movie;
 mod nes;
+mod nestest;
+mod nsf;
+mod ntsc;
+mod palette;
+mod patch;
+mod png;
+mod region;
+mod state;
+mod unif;
 
 fn main() {
-    let filepath = std::env::args().nth(1).expect("NES file path not provided");
+    let args = args::parse_args();
 
-    let file_buff = fs::read(filepath).expect("Could not read NES file");
+    if !args.cpu_tests.is_empty() {
+        std::process::exit(run_cpu_tests(&args.cpu_tests));
+    }
 
-    let file = nes::parse_nes_file(&file_buff).unwrap();
-    let mut emu = emu::Emulator::new(file_buff, file);
+    if !args.test_roms.is_empty() {
+        std::process::exit(run_test_roms(&args));
+    }
+
+    let file = load_rom(&args.rom_path, args.patch_path.as_deref(), &args).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+
+    let mut emu = emu::Emulator::new(file).unwrap_or_else(|err| {
+        eprintln!("Could not start the ROM: {}", err);
+        std::process::exit(1);
+    });
+    emu.set_power_on_ram(args.power_on_ram);
+    emu.set_window_scale(args.scale);
+    if let Some(magic) = args.unstable_magic {
+        emu.set_unstable_magic(magic);
+    }
+    if args.log_unofficial {
+        emu.enable_unofficial_log();
+    }
+    if args.debug {
+        emu.enable_debugger();
+    }
+    if let Some(preset) = args.ntsc_filter {
+        emu.set_ntsc_filter(ntsc::NtscFilter::new(preset, args.ntsc_params));
+    }
+    if let Some(palette) = &args.palette {
+        let palette = match palette {
+            args::PaletteSource::File(path) => palette::Palette::load(path),
+            args::PaletteSource::Ntsc => Ok(palette::Palette::generate_ntsc(&args.ntsc_params)),
+        };
+        match palette {
+            Ok(palette) => emu.set_palette(palette),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(params) = args.crt {
+        emu.set_crt_filter(params);
+    }
+    emu.set_state_path(args.rom_path.clone());
+    emu.configure_rewind(
+        args.rewind_interval.unwrap_or(emu::DEFAULT_REWIND_INTERVAL),
+        args.rewind_budget
+            .map_or(emu::DEFAULT_REWIND_BUDGET, |mib| mib * 1024 * 1024),
+    );
+
+    let save_path = args.rom_path.with_extension("sav");
+    if let Ok(save_data) = fs::read(&save_path) {
+        emu.load_save_data(&save_data);
+    }
+
+    if let Some(slot) = args.from_state {
+        emu.select_state_slot(slot);
+        if let Err(err) = emu.load_state_slot() {
+            eprintln!("Could not load state from slot {}: {}", slot, err);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(path) = &args.play_path {
+        let movie = movie::Movie::load(path).and_then(|movie| emu.play_movie(movie));
+        if let Err(err) = movie {
+            eprintln!("Could not play movie: {}", err);
+            std::process::exit(1);
+        }
+    } else if let Some(path) = &args.record_path {
+        let rom_name = args.rom_path.file_stem().unwrap_or_default();
+        emu.record_movie(
+            path.clone(),
+            rom_name.to_string_lossy().into_owned(),
+            args.from_state.is_some(),
+        );
+    }
+
+    if let Some(path) = &args.nestest_log {
+        let log = fs::read_to_string(path).expect("Could not read nestest log");
+        std::process::exit(run_nestest(&mut emu, &log));
+    }
+
+    if args.headless {
+        std::process::exit(run_headless(&mut emu, &args));
+    }
 
     emu.start_emulation();
+    if let Err(err) = emu.finish_movie() {
+        eprintln!("Could not write movie: {}", err);
+    }
+
+    if let Some(save_data) = emu.save_data() {
+        fs::write(&save_path, save_data).expect("Could not write save file");
+    }
+}
+
+/// Runs the requested number of frames without a window and writes the outputs, returns the exit status
+fn run_headless(emu: &mut emu::Emulator, args: &args::Args) -> i32 {
+    let result = emu.run_headless(args.frames.unwrap_or_default());
+
+    let hash = emu.frame_hash();
+    println!("Frame hash: {:08X}", hash);
+
+    let mut status = 0;
+
+    if let Err(err) = emu.finish_movie() {
+        eprintln!("Could not write movie: {}", err);
+        status = 1;
+    }
+
+    if let Err(fault) = result {
+        eprintln!("Emulation fault: {}", fault);
+        eprintln!("State at the end of the run: {}", emu.state_dump());
+
+        if matches!(fault, emu::EmulationFault::CpuJammed { .. }) {
+            status = 1;
+        }
+    }
+
+    if let Some(path) = &args.screenshot_path {
+        let (width, height, pixels) = emu.output_frame();
+        let png = png::encode_png(width, height, pixels);
+        if let Err(err) = fs::write(path, png) {
+            eprintln!("Could not write {}: {}", path.display(), err);
+            status = 1;
+        }
+    }
+
+    if let Some(path) = &args.ram_path {
+        if let Err(err) = fs::write(path, emu.internal_ram()) {
+            eprintln!("Could not write {}: {}", path.display(), err);
+            status = 1;
+        }
+    }
+
+    if let Some(expected) = args.expect_hash {
+        if hash != expected {
+            eprintln!(
+                "Frame hash mismatch: expected {:08X}, got {:08X}",
+                expected, hash
+            );
+            status = 1;
+        }
+    }
+
+    status
+}
+
+/// Diffs the nestest.nes trace against the reference log, returns the exit status
+fn run_nestest(emu: &mut emu::Emulator, log: &str) -> i32 {
+    match nestest::run_nestest(emu, log) {
+        Ok(matched) => {
+            println!("nestest: all {} instructions match", matched);
+            0
+        }
+        Err(divergence) => {
+            println!("nestest: first divergence at line {}", divergence.line);
+            println!("expected: {}", divergence.expected);
+            println!("actual:   {}", divergence.actual);
+            1
+        }
+    }
+}
+
+/// Reads, patches and parses the ROM or disk image
+fn load_rom(
+    rom_path: &Path,
+    patch_path: Option<&Path>,
+    args: &args::Args,
+) -> Result<nes::NESFile, String> {
+    let mut file_buff =
+        fs::read(rom_path).map_err(|err| format!("Could not read NES file: {}", err))?;
+
+    let patch = match patch_path {
+        Some(path) => {
+            Some(fs::read(path).map_err(|err| format!("Could not read patch file: {}", err))?)
+        }
+        None => patch::find_patch(rom_path)
+            .map_err(|err| format!("Could not read patch file: {}", err))?,
+    };
+
+    if let Some(patch) = patch {
+        file_buff = patch::apply_patch(&file_buff, &patch)
+            .map_err(|err| format!("Could not apply patch: {}", err))?;
+    }
+
+    let mut file = if fds::is_fds_file(&file_buff) {
+        let bios = fs::read(args.bios_path())
+            .map_err(|err| format!("Could not read FDS BIOS: {}", err))?;
+        fds::parse_fds_file(&file_buff, &bios)
+    } else if nsf::is_nsf_file(&file_buff) {
+        nsf::parse_nsf_file(&file_buff)
+    } else if unif::is_unif_file(&file_buff) {
+        unif::parse_unif_file(&file_buff)
+    } else {
+        nes::parse_nes_file(&file_buff)
+    }
+    .map_err(|_| "Could not parse ROM file".to_string())?;
+
+    // there is no game database, ROMs without a region in the header run as NTSC unless
+    // overridden on the command line
+    file.region = args.region.or(file.region);
+
+    Ok(file)
+}
+
+/// Runs every test ROM and prints a table of the results, returns the exit status
+fn run_test_roms(args: &args::Args) -> i32 {
+    let max_frames = args.frames.unwrap_or(blargg::DEFAULT_MAX_FRAMES);
+    let name_width = args
+        .test_roms
+        .iter()
+        .map(|path| path.display().to_string().len())
+        .max()
+        .unwrap_or_default();
+
+    println!("{:<width$}  Result", "ROM", width = name_width);
+
+    let mut failures = 0;
+    for path in &args.test_roms {
+        let result = match load_rom(path, None, args) {
+            Ok(file) => blargg::run_test_rom(file, max_frames),
+            Err(err) => blargg::TestResult::error(err),
+        };
+
+        if !result.passed() {
+            failures += 1;
+        }
+
+        println!("{:<width$}  {}", path.display(), result, width = name_width);
+    }
+
+    println!(
+        "{}/{} passed",
+        args.test_roms.len() - failures,
+        args.test_roms.len()
+    );
+
+    if failures > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// Runs the per-opcode JSON tests against the CPU, returns the exit status
+fn run_cpu_tests(paths: &[std::path::PathBuf]) -> i32 {
+    let mut emu = emu::Emulator::with_test_bus();
+
+    println!("Opcode    Tests  State  Cycles    Bus");
+
+    let mut failures = 0;
+    for path in cputest::collect_test_files(paths) {
+        let Ok(report) = cputest::run_test_file(&mut emu, &path) else {
+            println!("{:<8}  could not read {}", "?", path.display());
+            failures += 1;
+            continue;
+        };
+
+        println!("{}", report);
+        if let Some(failure) = &report.first_failure {
+            println!("        first failure {}", failure);
+        }
+
+        if !report.passed() {
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        1
+    } else {
+        0
+    }
 }
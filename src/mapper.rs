@@ -1,37 +1,106 @@
-use crate::nes::NESFile;
+use std::fmt;
 
-use self::nrom::NROMMapper;
+use crate::{
+    fds::FDS_MAPPER_NUMBER,
+    nes::{MirroringMode, NESFile},
+    nsf::NSF_MAPPER_NUMBER,
+    state::{StateError, StateReader, StateWriter},
+};
 
+use self::{fds::FDSMapper, nrom::NROMMapper, nsf::NSFMapper};
+
+mod fds;
 mod nrom;
+mod nsf;
+
+/// Why the cartridge couldn't complete an access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapperError {
+    /// Nothing on the cartridge responds at the address
+    Unmapped,
+
+    /// The cartridge has hardware at the address that isn't emulated
+    Unsupported(&'static str),
+}
+
+/// The cartridge uses a mapper that isn't emulated
+#[derive(Debug)]
+pub struct UnsupportedMapper(pub u8);
+
+impl fmt::Display for UnsupportedMapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported mapper {}", self.0)
+    }
+}
 
 pub trait Mapper {
     /// Instantiates a new mapper
-    fn new(file_buff: &[u8], nes_file: &NESFile) -> Self
+    fn new(nes_file: &NESFile) -> Self
     where
         Self: Sized;
 
-    /// Read from PRG memory
-    fn read_cpu(&self, addr: u16) -> Result<u8, ()>;
+    /// Read from PRG memory, reading can have side effects on registers
+    fn read_cpu(&mut self, addr: u16) -> Result<u8, MapperError>;
+
+    /// Read from PRG memory without the side effects of `read_cpu`, for debugging
+    fn peek_cpu(&self, addr: u16) -> Result<u8, MapperError>;
 
     /// Write to PRG memory
-    fn write_cpu(&mut self, addr: u16, val: u8) -> Result<(), ()>;
+    fn write_cpu(&mut self, addr: u16, val: u8) -> Result<(), MapperError>;
 
     /// Read from CHR memory
-    fn read_ppu(&self, addr: u16) -> Result<u8, ()>;
+    fn read_ppu(&self, addr: u16) -> Result<u8, MapperError>;
 
     /// Write to CHR memory
-    fn write_ppu(&mut self, addr: u16, val: u8) -> Result<(), ()>;
+    fn write_ppu(&mut self, addr: u16, val: u8) -> Result<(), MapperError>;
 
     /// Returns the entry point(beginning of PRG memory)
     /// FIXME: get it from RESET interrupt vector
     fn entrypoint(&self) -> u16;
-}
 
-pub fn get_mapper(file_buff: &[u8], nes_file: &NESFile) -> Box<dyn Mapper> {
-    let mapper = match nes_file.mapper_number {
-        0 => NROMMapper::new(file_buff, nes_file),
-        _ => unreachable!(),
-    };
+    /// Returns the current nametable mirroring
+    fn mirroring_mode(&self) -> MirroringMode;
+
+    /// Serializes the state of the mapper for save states
+    fn save_state(&self, writer: &mut StateWriter);
+
+    /// Restores the state written by `save_state`
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError>;
+
+    /// Puts the registers back in their power-on state, the cartridge doesn't see the reset button
+    fn power_cycle(&mut self) {}
+
+    /// Called on every CPU cycle
+    fn clock_cpu(&mut self) {}
+
+    /// Returns whether the mapper is asserting the IRQ line
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Returns the output of the expansion audio in the range 0.0-1.0
+    fn expansion_audio(&self) -> f32 {
+        0.0
+    }
+
+    /// Ejects the current disk side and inserts the next one
+    fn switch_disk_side(&mut self) {}
+
+    /// Returns the data that has to persist between sessions
+    fn save_data(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores the data returned by `save_data`
+    fn load_save_data(&mut self, _data: &[u8]) {}
+}
 
-    Box::new(mapper)
+/// Whether `get_mapper` can create the mapper
+pub fn get_mapper(nes_file: &NESFile) -> Result<Box<dyn Mapper>, UnsupportedMapper> {
+    match nes_file.mapper_number {
+        0 => Ok(Box::new(NROMMapper::new(nes_file))),
+        FDS_MAPPER_NUMBER => Ok(Box::new(FDSMapper::new(nes_file))),
+        NSF_MAPPER_NUMBER => Ok(Box::new(NSFMapper::new(nes_file))),
+        mapper_number => Err(UnsupportedMapper(mapper_number)),
+    }
 }
@@ -0,0 +1,634 @@
+use crate::{
+    fds::disk_blocks,
+    nes::{MirroringMode, NESFile},
+    patch,
+    state::{StateError, StateReader, StateWriter},
+};
+
+use self::audio::FDSAudio;
+
+use super::{Mapper, MapperError};
+
+pub mod audio;
+
+/// 32 KiB PRG RAM at 0x6000-0xDFFF
+const PRG_RAM_SIZE: usize = usize::pow(2, 15);
+
+/// 8 KiB CHR RAM
+const CHR_RAM_SIZE: usize = usize::pow(2, 13);
+
+/// Gap before the first block(28300 bits)
+const LEADING_GAP_SIZE: usize = 28300 / 8;
+
+/// Gap after every block(976 bits)
+const BLOCK_GAP_SIZE: usize = 976 / 8;
+
+/// Size of a side with the gaps, block start markers and CRCs
+const RAW_SIDE_SIZE: usize = 68000;
+
+/// Written before every block to mark the end of the gap
+const BLOCK_START_MARK: u8 = 0x80;
+
+/// CPU cycles it takes to transfer a byte(~96.4 kHz bit rate)
+const BYTE_TRANSFER_CYCLES: usize = 150;
+
+/// CPU cycles it takes for the head to return to the beginning of the disk
+const HEAD_RETURN_CYCLES: usize = 50000;
+
+/// CPU cycles the drive is left empty for when switching sides(~1 second)
+const EJECT_CYCLES: usize = 1_789_773;
+
+/// Updates the CRC-16 used by the FDS with one byte
+fn update_crc(crc: u16, val: u8) -> u16 {
+    let mut crc = crc;
+
+    for bit in 0..8 {
+        let carry = crc & 1 > 0;
+        crc >>= 1;
+        if carry {
+            crc ^= 0x8408;
+        }
+        if val & (1 << bit) > 0 {
+            crc ^= 0x8000;
+        }
+    }
+
+    crc
+}
+
+/// Converts a side from the .fds layout to how it is laid out on the disk
+fn build_raw_side(side: &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEADING_GAP_SIZE];
+
+    for block in disk_blocks(side, 0) {
+        let block_start = raw.len();
+        raw.push(BLOCK_START_MARK);
+        raw.extend_from_slice(block);
+
+        let crc = raw[block_start..]
+            .iter()
+            .chain([0, 0].iter())
+            .fold(0, |crc, &val| update_crc(crc, val));
+
+        raw.extend_from_slice(&crc.to_le_bytes());
+        raw.resize(raw.len() + BLOCK_GAP_SIZE, 0);
+    }
+
+    raw.resize(RAW_SIDE_SIZE.max(raw.len()), 0);
+    raw
+}
+
+// https://www.nesdev.org/wiki/Family_Computer_Disk_System
+pub struct FDSMapper {
+    bios: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr_ram: Vec<u8>,
+
+    /// Sides as they were loaded, used to create the save diff
+    original_sides: Vec<Vec<u8>>,
+    sides: Vec<Vec<u8>>,
+    current_side: Option<usize>,
+    next_side: usize,
+    eject_counter: usize,
+
+    irq_reload: u16,
+    irq_counter: u16,
+    irq_repeat: bool,
+    irq_enabled: bool,
+    timer_irq: bool,
+
+    disk_regs_enabled: bool,
+    sound_regs_enabled: bool,
+
+    motor_on: bool,
+    reset_transfer: bool,
+    read_mode: bool,
+    mirroring_mode: MirroringMode,
+    crc_control: bool,
+    disk_ready: bool,
+    disk_irq_enabled: bool,
+    disk_irq: bool,
+
+    position: usize,
+    delay: usize,
+    scanning: bool,
+    end_of_head: bool,
+    gap_ended: bool,
+    transfer_complete: bool,
+    read_data: u8,
+    write_data: u8,
+    crc: u16,
+    previous_crc_control: bool,
+
+    external_output: u8,
+    audio: FDSAudio,
+}
+
+impl FDSMapper {
+    fn clock_timer_irq(&mut self) {
+        if !self.irq_enabled || !self.disk_regs_enabled {
+            return;
+        }
+
+        if self.irq_counter == 0 {
+            self.timer_irq = true;
+            self.irq_counter = self.irq_reload;
+            if !self.irq_repeat {
+                self.irq_enabled = false;
+            }
+        } else {
+            self.irq_counter -= 1;
+        }
+    }
+
+    fn clock_disk(&mut self) {
+        if self.eject_counter > 0 {
+            self.eject_counter -= 1;
+            if self.eject_counter == 0 {
+                self.current_side = Some(self.next_side);
+            }
+        }
+
+        let side = match self.current_side {
+            Some(side) if self.motor_on => side,
+            _ => {
+                self.end_of_head = true;
+                self.scanning = false;
+                return;
+            }
+        };
+
+        if self.reset_transfer && !self.scanning {
+            return;
+        }
+
+        if self.end_of_head {
+            self.delay = HEAD_RETURN_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+
+        if self.read_mode {
+            self.read_disk_byte(side);
+        } else {
+            self.write_disk_byte(side);
+        }
+
+        self.previous_crc_control = self.crc_control;
+
+        self.position += 1;
+        if self.position >= self.sides[side].len() {
+            self.motor_on = false;
+        } else {
+            self.delay = BYTE_TRANSFER_CYCLES;
+        }
+    }
+
+    fn read_disk_byte(&mut self, side: usize) {
+        let val = self.sides[side][self.position];
+
+        if !self.disk_ready {
+            self.gap_ended = false;
+        } else if !self.gap_ended {
+            // the start mark is transferred but doesn't generate an IRQ
+            if val == BLOCK_START_MARK {
+                self.gap_ended = true;
+                self.read_data = val;
+                self.transfer_complete = true;
+            }
+        } else {
+            self.read_data = val;
+            self.transfer_complete = true;
+            if self.disk_irq_enabled {
+                self.disk_irq = true;
+            }
+        }
+    }
+
+    fn write_disk_byte(&mut self, side: usize) {
+        let mut val = self.write_data;
+
+        if !self.crc_control {
+            self.transfer_complete = true;
+            if self.disk_irq_enabled {
+                self.disk_irq = true;
+            }
+        }
+
+        if !self.disk_ready {
+            // writing the gap
+            val = 0;
+            self.crc = 0;
+        } else if !self.crc_control {
+            self.crc = update_crc(self.crc, val);
+        } else {
+            if !self.previous_crc_control {
+                self.crc = update_crc(update_crc(self.crc, 0), 0);
+            }
+
+            val = self.crc as u8;
+            self.crc >>= 8;
+        }
+
+        self.sides[side][self.position] = val;
+        self.gap_ended = false;
+    }
+}
+
+impl Mapper for FDSMapper {
+    fn new(nes_file: &NESFile) -> Self {
+        let sides: Vec<Vec<u8>> = nes_file
+            .disk_sides
+            .iter()
+            .map(|side| build_raw_side(side))
+            .collect();
+
+        Self {
+            bios: nes_file.prg_rom.clone(),
+            prg_ram: vec![0; PRG_RAM_SIZE],
+            chr_ram: vec![0; CHR_RAM_SIZE],
+
+            original_sides: sides.clone(),
+            sides,
+            current_side: Some(0),
+            next_side: 0,
+            eject_counter: 0,
+
+            irq_reload: 0,
+            irq_counter: 0,
+            irq_repeat: false,
+            irq_enabled: false,
+            timer_irq: false,
+
+            disk_regs_enabled: false,
+            sound_regs_enabled: false,
+
+            motor_on: false,
+            reset_transfer: false,
+            read_mode: true,
+            mirroring_mode: MirroringMode::Horizontal,
+            crc_control: false,
+            disk_ready: false,
+            disk_irq_enabled: false,
+            disk_irq: false,
+
+            position: 0,
+            delay: 0,
+            scanning: false,
+            end_of_head: true,
+            gap_ended: false,
+            transfer_complete: false,
+            read_data: 0,
+            write_data: 0,
+            crc: 0,
+            previous_crc_control: false,
+
+            external_output: 0,
+            audio: FDSAudio::new(),
+        }
+    }
+
+    fn read_cpu(&mut self, addr: u16) -> Result<u8, MapperError> {
+        let val = self.peek_cpu(addr)?;
+
+        match addr {
+            // reading acknowledges both IRQs
+            0x4030 => {
+                self.timer_irq = false;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4031 => {
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            _ => {}
+        }
+
+        Ok(val)
+    }
+
+    fn peek_cpu(&self, addr: u16) -> Result<u8, MapperError> {
+        match addr {
+            0x4030 => {
+                let mut res = 0;
+                if self.timer_irq {
+                    res |= 1 << 0;
+                }
+                if self.transfer_complete {
+                    res |= 1 << 1;
+                }
+                if self.end_of_head {
+                    res |= 1 << 6;
+                }
+
+                Ok(res)
+            }
+            0x4031 => Ok(self.read_data),
+            0x4032 => {
+                let inserted = self.current_side.is_some();
+
+                let mut res = 0x40;
+                if !inserted {
+                    // disk missing, not ready and write protected
+                    res |= 0b101;
+                }
+                if !inserted || !self.scanning {
+                    res |= 1 << 1;
+                }
+
+                Ok(res)
+            }
+            // battery is good
+            0x4033 => Ok(0x80 | self.external_output & 0x7F),
+            0x4020..=0x402F => Ok(0),
+            0x4040..=0x4097 => Ok(self.audio.read(addr)),
+            0x6000..=0xDFFF => Ok(self.prg_ram[addr as usize - 0x6000]),
+            0xE000..=0xFFFF => Ok(self.bios[addr as usize - 0xE000]),
+            _ => Err(MapperError::Unmapped),
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, val: u8) -> Result<(), MapperError> {
+        match addr {
+            0x4023 => {
+                self.disk_regs_enabled = val & 1 > 0;
+                self.sound_regs_enabled = val & 2 > 0;
+
+                if !self.disk_regs_enabled {
+                    self.irq_enabled = false;
+                    self.timer_irq = false;
+                    self.disk_irq = false;
+                }
+            }
+            0x4020..=0x4026 if !self.disk_regs_enabled => {}
+            0x4020 => self.irq_reload = self.irq_reload & 0xFF00 | val as u16,
+            0x4021 => self.irq_reload = self.irq_reload & 0x00FF | (val as u16) << 8,
+            0x4022 => {
+                self.irq_repeat = val & 1 > 0;
+                self.irq_enabled = val & 2 > 0;
+
+                if self.irq_enabled {
+                    self.irq_counter = self.irq_reload;
+                } else {
+                    self.timer_irq = false;
+                }
+            }
+            0x4024 => {
+                self.write_data = val;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4025 => {
+                self.motor_on = val & 1 > 0;
+                self.reset_transfer = val & 2 > 0;
+                self.read_mode = val & 4 > 0;
+                self.mirroring_mode = if val & 8 > 0 {
+                    MirroringMode::Horizontal
+                } else {
+                    MirroringMode::Vertical
+                };
+                self.crc_control = val & 0x10 > 0;
+                self.disk_ready = val & 0x40 > 0;
+                self.disk_irq_enabled = val & 0x80 > 0;
+                self.disk_irq = false;
+            }
+            0x4026 => self.external_output = val,
+            0x4040..=0x408A => {
+                if self.sound_regs_enabled {
+                    self.audio.write(addr, val);
+                }
+            }
+            0x6000..=0xDFFF => self.prg_ram[addr as usize - 0x6000] = val,
+            // writes to the BIOS are ignored
+            0xE000..=0xFFFF => {}
+            _ => return Err(MapperError::Unmapped),
+        }
+
+        Ok(())
+    }
+
+    fn read_ppu(&self, addr: u16) -> Result<u8, MapperError> {
+        if addr >= 0x2000 {
+            return Err(MapperError::Unmapped);
+        }
+
+        Ok(self.chr_ram[addr as usize])
+    }
+
+    fn write_ppu(&mut self, addr: u16, val: u8) -> Result<(), MapperError> {
+        if addr >= 0x2000 {
+            return Err(MapperError::Unmapped);
+        }
+
+        self.chr_ram[addr as usize] = val;
+        Ok(())
+    }
+
+    fn entrypoint(&self) -> u16 {
+        u16::from_le_bytes([self.bios[0x1FFC], self.bios[0x1FFD]])
+    }
+
+    /// The disks and the RAM keep their contents
+    fn power_cycle(&mut self) {
+        self.irq_reload = 0;
+        self.irq_counter = 0;
+        self.irq_repeat = false;
+        self.irq_enabled = false;
+        self.timer_irq = false;
+
+        self.disk_regs_enabled = false;
+        self.sound_regs_enabled = false;
+
+        self.motor_on = false;
+        self.reset_transfer = false;
+        self.read_mode = true;
+        self.mirroring_mode = MirroringMode::Horizontal;
+        self.crc_control = false;
+        self.disk_ready = false;
+        self.disk_irq_enabled = false;
+        self.disk_irq = false;
+
+        self.position = 0;
+        self.delay = 0;
+        self.scanning = false;
+        self.end_of_head = true;
+        self.gap_ended = false;
+        self.transfer_complete = false;
+
+        self.external_output = 0;
+        self.audio = FDSAudio::new();
+    }
+
+    fn mirroring_mode(&self) -> MirroringMode {
+        self.mirroring_mode
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.prg_ram);
+        writer.write_bytes(&self.chr_ram);
+
+        for side in &self.sides {
+            writer.write_blob(side);
+        }
+        writer.write_bool(self.current_side.is_some());
+        writer.write_usize(self.current_side.unwrap_or(0));
+        writer.write_usize(self.next_side);
+        writer.write_usize(self.eject_counter);
+
+        writer.write_u16(self.irq_reload);
+        writer.write_u16(self.irq_counter);
+        writer.write_bool(self.irq_repeat);
+        writer.write_bool(self.irq_enabled);
+        writer.write_bool(self.timer_irq);
+
+        writer.write_bool(self.disk_regs_enabled);
+        writer.write_bool(self.sound_regs_enabled);
+
+        writer.write_bool(self.motor_on);
+        writer.write_bool(self.reset_transfer);
+        writer.write_bool(self.read_mode);
+        writer.write_bool(matches!(self.mirroring_mode, MirroringMode::Horizontal));
+        writer.write_bool(self.crc_control);
+        writer.write_bool(self.disk_ready);
+        writer.write_bool(self.disk_irq_enabled);
+        writer.write_bool(self.disk_irq);
+
+        writer.write_usize(self.position);
+        writer.write_usize(self.delay);
+        writer.write_bool(self.scanning);
+        writer.write_bool(self.end_of_head);
+        writer.write_bool(self.gap_ended);
+        writer.write_bool(self.transfer_complete);
+        writer.write_u8(self.read_data);
+        writer.write_u8(self.write_data);
+        writer.write_u16(self.crc);
+        writer.write_bool(self.previous_crc_control);
+
+        writer.write_u8(self.external_output);
+        self.audio.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        reader.read_into(&mut self.prg_ram)?;
+        reader.read_into(&mut self.chr_ram)?;
+
+        for side in self.sides.iter_mut() {
+            reader.read_blob_into(side)?;
+        }
+        let inserted = reader.read_bool()?;
+        let current_side = reader.read_usize()?;
+        let next_side = reader.read_usize()?;
+        if current_side >= self.sides.len() || next_side >= self.sides.len() {
+            return Err(StateError::Corrupted);
+        }
+
+        self.current_side = inserted.then_some(current_side);
+        self.next_side = next_side;
+        self.eject_counter = reader.read_usize()?;
+
+        self.irq_reload = reader.read_u16()?;
+        self.irq_counter = reader.read_u16()?;
+        self.irq_repeat = reader.read_bool()?;
+        self.irq_enabled = reader.read_bool()?;
+        self.timer_irq = reader.read_bool()?;
+
+        self.disk_regs_enabled = reader.read_bool()?;
+        self.sound_regs_enabled = reader.read_bool()?;
+
+        self.motor_on = reader.read_bool()?;
+        self.reset_transfer = reader.read_bool()?;
+        self.read_mode = reader.read_bool()?;
+        self.mirroring_mode = if reader.read_bool()? {
+            MirroringMode::Horizontal
+        } else {
+            MirroringMode::Vertical
+        };
+        self.crc_control = reader.read_bool()?;
+        self.disk_ready = reader.read_bool()?;
+        self.disk_irq_enabled = reader.read_bool()?;
+        self.disk_irq = reader.read_bool()?;
+
+        self.position = reader.read_usize()?;
+        self.delay = reader.read_usize()?;
+        self.scanning = reader.read_bool()?;
+        self.end_of_head = reader.read_bool()?;
+        self.gap_ended = reader.read_bool()?;
+        self.transfer_complete = reader.read_bool()?;
+        self.read_data = reader.read_u8()?;
+        self.write_data = reader.read_u8()?;
+        self.crc = reader.read_u16()?;
+        self.previous_crc_control = reader.read_bool()?;
+
+        self.external_output = reader.read_u8()?;
+        self.audio.load_state(reader)
+    }
+
+    fn clock_cpu(&mut self) {
+        self.clock_timer_irq();
+        self.clock_disk();
+        self.audio.clock();
+    }
+
+    fn irq(&self) -> bool {
+        self.timer_irq || self.disk_irq
+    }
+
+    fn expansion_audio(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn switch_disk_side(&mut self) {
+        self.next_side = match self.current_side {
+            Some(side) => side + 1,
+            None => self.next_side + 1,
+        } % self.sides.len();
+
+        self.current_side = None;
+        self.eject_counter = EJECT_CYCLES;
+
+        eprintln!(
+            "Inserting disk {} side {}",
+            self.next_side / 2 + 1,
+            ['A', 'B'][self.next_side % 2]
+        );
+    }
+
+    fn save_data(&self) -> Option<Vec<u8>> {
+        if self.sides == self.original_sides {
+            return None;
+        }
+
+        Some(patch::create_ips_patch(
+            &self.original_sides.concat(),
+            &self.sides.concat(),
+        ))
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        let original = self.original_sides.concat();
+        let patched = match patch::apply_patch(&original, data) {
+            Ok(patched) if patched.len() == original.len() => patched,
+            _ => {
+                eprintln!("Disk save data is invalid, ignoring it");
+                return;
+            }
+        };
+
+        let mut off = 0;
+        for side in self.sides.iter_mut() {
+            let len = side.len();
+            side.copy_from_slice(&patched[off..off + len]);
+            off += len;
+        }
+    }
+}
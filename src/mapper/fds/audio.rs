@@ -0,0 +1,321 @@
+use crate::state::{StateError, StateReader, StateWriter};
+
+/// Master volume levels out of 30
+const MASTER_VOLUMES: [u32; 4] = [30, 20, 15, 12];
+
+/// How the modulation counter changes for each modulation table value
+const MOD_ADJUSTMENTS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+
+/// Modulation table value that resets the counter
+const MOD_RESET: u8 = 4;
+
+/// Largest gain an envelope can apply to the output
+const MAX_OUTPUT_GAIN: u8 = 32;
+
+/// Largest possible output(wave sample * gain * master volume)
+const MAX_OUTPUT: f32 = (63 * MAX_OUTPUT_GAIN as u32 * 30) as f32;
+
+struct Envelope {
+    speed: u8,
+    gain: u8,
+    increase: bool,
+    disabled: bool,
+    counter: usize,
+}
+
+impl Envelope {
+    fn new() -> Envelope {
+        Envelope {
+            speed: 0,
+            gain: 0,
+            increase: false,
+            disabled: true,
+            counter: 0,
+        }
+    }
+
+    fn write(&mut self, val: u8) {
+        self.disabled = val & 0x80 > 0;
+        self.increase = val & 0x40 > 0;
+        self.speed = val & 0x3F;
+        self.counter = 0;
+
+        if self.disabled {
+            self.gain = val & 0x3F;
+        }
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.speed);
+        writer.write_u8(self.gain);
+        writer.write_bool(self.increase);
+        writer.write_bool(self.disabled);
+        writer.write_usize(self.counter);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        self.speed = reader.read_u8()?;
+        self.gain = reader.read_u8()?;
+        self.increase = reader.read_bool()?;
+        self.disabled = reader.read_bool()?;
+        self.counter = reader.read_usize()?;
+        Ok(())
+    }
+
+    fn clock(&mut self, master_speed: u8) {
+        if self.disabled {
+            return;
+        }
+
+        if self.counter > 0 {
+            self.counter -= 1;
+            return;
+        }
+
+        self.counter = 8 * (self.speed as usize + 1) * master_speed as usize;
+
+        if self.increase {
+            if self.gain < MAX_OUTPUT_GAIN {
+                self.gain += 1;
+            }
+        } else if self.gain > 0 {
+            self.gain -= 1;
+        }
+    }
+}
+
+// https://www.nesdev.org/wiki/FDS_audio
+pub struct FDSAudio {
+    wave_table: [u8; 64],
+    wave_write_enabled: bool,
+    wave_halted: bool,
+    wave_frequency: u16,
+    wave_accumulator: u32,
+    /// Volume gain latched at the start of every wave cycle
+    output_gain: u8,
+
+    envelopes_halted: bool,
+    envelope_speed: u8,
+    volume_envelope: Envelope,
+    mod_envelope: Envelope,
+
+    mod_table: [u8; 64],
+    mod_position: usize,
+    mod_halted: bool,
+    mod_frequency: u16,
+    mod_accumulator: u16,
+    /// 7-bit signed
+    mod_counter: i8,
+
+    master_volume: u8,
+}
+
+impl FDSAudio {
+    pub fn new() -> FDSAudio {
+        FDSAudio {
+            wave_table: [0; 64],
+            wave_write_enabled: false,
+            wave_halted: true,
+            wave_frequency: 0,
+            wave_accumulator: 0,
+            output_gain: 0,
+
+            envelopes_halted: false,
+            envelope_speed: 0xE8,
+            volume_envelope: Envelope::new(),
+            mod_envelope: Envelope::new(),
+
+            mod_table: [0; 64],
+            mod_position: 0,
+            mod_halted: true,
+            mod_frequency: 0,
+            mod_accumulator: 0,
+            mod_counter: 0,
+
+            master_volume: 0,
+        }
+    }
+
+    pub fn read(&self, addr: u16) -> u8 {
+        match addr {
+            0x4040..=0x407F => self.wave_table[addr as usize - 0x4040] | 0x40,
+            0x4090 => self.volume_envelope.gain | 0x40,
+            0x4092 => self.mod_envelope.gain | 0x40,
+            _ => 0x40,
+        }
+    }
+
+    pub fn write(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x4040..=0x407F => {
+                if self.wave_write_enabled {
+                    self.wave_table[addr as usize - 0x4040] = val & 0x3F;
+                }
+            }
+            0x4080 => self.volume_envelope.write(val),
+            0x4082 => self.wave_frequency = self.wave_frequency & 0xF00 | val as u16,
+            0x4083 => {
+                self.wave_frequency = self.wave_frequency & 0xFF | (val as u16 & 0xF) << 8;
+                self.envelopes_halted = val & 0x40 > 0;
+                self.wave_halted = val & 0x80 > 0;
+
+                if self.wave_halted {
+                    self.wave_accumulator = 0;
+                }
+            }
+            0x4084 => self.mod_envelope.write(val),
+            0x4085 => self.mod_counter = ((val << 1) as i8) >> 1,
+            0x4086 => self.mod_frequency = self.mod_frequency & 0xF00 | val as u16,
+            0x4087 => {
+                self.mod_frequency = self.mod_frequency & 0xFF | (val as u16 & 0xF) << 8;
+                self.mod_halted = val & 0x80 > 0;
+
+                if self.mod_halted {
+                    self.mod_accumulator = 0;
+                }
+            }
+            0x4088 => {
+                // every entry is used for two steps
+                if self.mod_halted {
+                    self.mod_table[self.mod_position] = val & 0b111;
+                    self.mod_table[self.mod_position + 1] = val & 0b111;
+                    self.mod_position = (self.mod_position + 2) % self.mod_table.len();
+                }
+            }
+            0x4089 => {
+                self.wave_write_enabled = val & 0x80 > 0;
+                self.master_volume = val & 0b11;
+            }
+            0x408A => self.envelope_speed = val,
+            _ => {}
+        }
+    }
+
+    /// Wave frequency after the modulation unit is applied
+    fn modulated_frequency(&self) -> u32 {
+        let frequency = self.wave_frequency as i32;
+        if self.mod_halted {
+            return frequency as u32;
+        }
+
+        let mut temp = self.mod_counter as i32 * self.mod_envelope.gain as i32;
+        let remainder = temp & 0xF;
+        temp >>= 4;
+        if remainder > 0 && temp & 0x80 == 0 {
+            temp += if self.mod_counter < 0 { -1 } else { 2 };
+        }
+
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+
+        temp *= frequency;
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+
+        (frequency + temp).max(0) as u32
+    }
+
+    fn step_modulator(&mut self) {
+        let val = self.mod_table[self.mod_position];
+        self.mod_position = (self.mod_position + 1) % self.mod_table.len();
+
+        let counter = if val == MOD_RESET {
+            0
+        } else {
+            self.mod_counter as i16 + MOD_ADJUSTMENTS[val as usize] as i16
+        };
+
+        // wrap around to 7 bits
+        self.mod_counter = (((counter + 64) & 0x7F) - 64) as i8;
+    }
+
+    /// Called on every CPU cycle
+    pub fn clock(&mut self) {
+        if !self.envelopes_halted && !self.wave_halted && self.envelope_speed > 0 {
+            self.volume_envelope.clock(self.envelope_speed);
+            self.mod_envelope.clock(self.envelope_speed);
+        }
+
+        if !self.mod_halted && self.mod_frequency > 0 {
+            let (accumulator, overflow) = self.mod_accumulator.overflowing_add(self.mod_frequency);
+            self.mod_accumulator = accumulator;
+            if overflow {
+                self.step_modulator();
+            }
+        }
+
+        if !self.wave_halted && !self.wave_write_enabled {
+            let previous_position = self.wave_position();
+            self.wave_accumulator = (self.wave_accumulator + self.modulated_frequency()) & 0x3FFFFF;
+
+            if self.wave_position() < previous_position {
+                self.output_gain = self.volume_envelope.gain.min(MAX_OUTPUT_GAIN);
+            }
+        }
+    }
+
+    fn wave_position(&self) -> usize {
+        (self.wave_accumulator >> 16) as usize
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.wave_table);
+        writer.write_bool(self.wave_write_enabled);
+        writer.write_bool(self.wave_halted);
+        writer.write_u16(self.wave_frequency);
+        writer.write_u32(self.wave_accumulator);
+        writer.write_u8(self.output_gain);
+
+        writer.write_bool(self.envelopes_halted);
+        writer.write_u8(self.envelope_speed);
+        self.volume_envelope.save_state(writer);
+        self.mod_envelope.save_state(writer);
+
+        writer.write_bytes(&self.mod_table);
+        writer.write_usize(self.mod_position);
+        writer.write_bool(self.mod_halted);
+        writer.write_u16(self.mod_frequency);
+        writer.write_u16(self.mod_accumulator);
+        writer.write_u8(self.mod_counter as u8);
+
+        writer.write_u8(self.master_volume);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        reader.read_into(&mut self.wave_table)?;
+        self.wave_write_enabled = reader.read_bool()?;
+        self.wave_halted = reader.read_bool()?;
+        self.wave_frequency = reader.read_u16()?;
+        self.wave_accumulator = reader.read_u32()?;
+        self.output_gain = reader.read_u8()?;
+
+        self.envelopes_halted = reader.read_bool()?;
+        self.envelope_speed = reader.read_u8()?;
+        self.volume_envelope.load_state(reader)?;
+        self.mod_envelope.load_state(reader)?;
+
+        reader.read_into(&mut self.mod_table)?;
+        self.mod_position = reader.read_usize()? % self.mod_table.len();
+        self.mod_halted = reader.read_bool()?;
+        self.mod_frequency = reader.read_u16()?;
+        self.mod_accumulator = reader.read_u16()?;
+        self.mod_counter = reader.read_u8()? as i8;
+
+        self.master_volume = reader.read_u8()? & 0b11;
+        Ok(())
+    }
+
+    /// Returns the current output in the range 0.0-1.0
+    pub fn output(&self) -> f32 {
+        let sample = self.wave_table[self.wave_position()] as u32;
+        let level = sample * self.output_gain as u32 * MASTER_VOLUMES[self.master_volume as usize];
+
+        level as f32 / MAX_OUTPUT
+    }
+}
@@ -1,88 +1,118 @@
-use super::Mapper;
+use crate::{
+    nes::{MirroringMode, CHR_ROM_UNIT},
+    state::{StateError, StateReader, StateWriter},
+};
 
-enum NROMMapperType {
-    NROM128,
-    NROM256,
-}
+use super::{Mapper, MapperError};
+
+/// Family Basic boards have 8 KiB of PRG RAM at $6000, test ROMs rely on it for their output
+const PRG_RAM_SIZE: usize = 0x2000;
 
 // https://www.nesdev.org/wiki/NROM
 pub struct NROMMapper {
-    typ: NROMMapperType,
     prg_rom: Vec<u8>,
     chr_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    mirroring_mode: MirroringMode,
 }
 
 impl NROMMapper {
-    fn translate_prg_address(&self, addr: u16) -> u16 {
-        match self.typ {
-            NROMMapperType::NROM128 => addr & 0x3FFF,
-            NROMMapperType::NROM256 => addr & 0x7FFF,
-        }
+    /// NROM-128 mirrors its 16 KiB at $C000, UNIF images can hold even smaller PRG chunks that
+    /// repeat the same way
+    fn translate_prg_address(&self, addr: u16) -> usize {
+        (addr as usize - 0x8000) % self.prg_rom.len()
+    }
+
+    /// CHR smaller than 8 KiB repeats over the pattern tables
+    fn translate_chr_address(&self, addr: u16) -> usize {
+        addr as usize % self.chr_rom.len()
     }
 }
 
 impl Mapper for NROMMapper {
-    fn new(file_buff: &[u8], nes_file: &crate::nes::NESFile) -> Self {
-        let prg_rom_size = nes_file.prg_rom_size as usize * usize::pow(2, 14);
-        // TODO: trainer
-        let prg_rom_start = 16;
-        let prg_rom_end = prg_rom_start + prg_rom_size;
-
-        let chr_rom_size = nes_file.chr_rom_size as usize * usize::pow(2, 13);
-        let chr_rom_start = prg_rom_end;
-        let chr_rom_end = chr_rom_start + chr_rom_size;
-
-        let prg_rom = Vec::from(&file_buff[prg_rom_start..prg_rom_end]);
-        let chr_rom = Vec::from(&file_buff[chr_rom_start..chr_rom_end]);
+    fn new(nes_file: &crate::nes::NESFile) -> Self {
+        let prg_rom = nes_file.prg_rom.clone();
+        // boards without CHR ROM have 8 KiB of CHR RAM
+        let chr_rom = if nes_file.chr_rom.is_empty() {
+            vec![0; CHR_ROM_UNIT]
+        } else {
+            nes_file.chr_rom.clone()
+        };
 
         Self {
-            typ: if nes_file.prg_rom_size == 1 {
-                NROMMapperType::NROM128
-            } else {
-                NROMMapperType::NROM256
-            },
             prg_rom,
             chr_rom,
+            prg_ram: vec![0; PRG_RAM_SIZE],
+            mirroring_mode: nes_file.mirroring_mode,
         }
     }
 
-    fn read_cpu(&self, addr: u16) -> Result<u8, ()> {
+    fn read_cpu(&mut self, addr: u16) -> Result<u8, MapperError> {
+        self.peek_cpu(addr)
+    }
+
+    fn peek_cpu(&self, addr: u16) -> Result<u8, MapperError> {
+        if (0x6000..0x8000).contains(&addr) {
+            return Ok(self.prg_ram[addr as usize - 0x6000]);
+        }
+
         if addr < 0x8000 {
-            return Err(());
+            return Err(MapperError::Unmapped);
         }
 
-        let addr = self.translate_prg_address(addr);
-        Ok(self.prg_rom[addr as usize])
+        let off = self.translate_prg_address(addr);
+        Ok(self.prg_rom[off])
     }
 
-    fn write_cpu(&mut self, addr: u16, val: u8) -> Result<(), ()> {
+    fn write_cpu(&mut self, addr: u16, val: u8) -> Result<(), MapperError> {
+        if (0x6000..0x8000).contains(&addr) {
+            self.prg_ram[addr as usize - 0x6000] = val;
+            return Ok(());
+        }
+
         if addr < 0x8000 {
-            return Err(());
+            return Err(MapperError::Unmapped);
         }
 
-        let addr = self.translate_prg_address(addr);
-        self.prg_rom[addr as usize] = val;
+        let off = self.translate_prg_address(addr);
+        self.prg_rom[off] = val;
         Ok(())
     }
 
-    fn read_ppu(&self, addr: u16) -> Result<u8, ()> {
+    fn read_ppu(&self, addr: u16) -> Result<u8, MapperError> {
         if addr >= 0x2000 {
-            return Err(());
+            return Err(MapperError::Unmapped);
         }
 
-        Ok(self.chr_rom[addr as usize])
+        Ok(self.chr_rom[self.translate_chr_address(addr)])
     }
 
-    fn write_ppu(&mut self, addr: u16, val: u8) -> Result<(), ()> {
+    fn write_ppu(&mut self, addr: u16, val: u8) -> Result<(), MapperError> {
         if addr >= 0x2000 {
-            return Err(());
+            return Err(MapperError::Unmapped);
         }
 
-        self.chr_rom[addr as usize] = val;
+        let off = self.translate_chr_address(addr);
+        self.chr_rom[off] = val;
         Ok(())
     }
 
     fn entrypoint(&self) -> u16 {
         0xC000
     }
+
+    fn mirroring_mode(&self) -> MirroringMode {
+        self.mirroring_mode
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        // CHR can be RAM
+        writer.write_blob(&self.chr_rom);
+        writer.write_bytes(&self.prg_ram);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        reader.read_blob_into(&mut self.chr_rom)?;
+        reader.read_into(&mut self.prg_ram)
+    }
 }
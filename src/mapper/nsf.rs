@@ -0,0 +1,390 @@
+use crate::{
+    nes::{MirroringMode, NESFile},
+    nsf::{
+        NSFInfo, CHIP_FDS, CHIP_MMC5, CHIP_N163, CHIP_S5B, CHIP_VRC6, CHIP_VRC7, DRIVER_ADDRESS,
+        PLAY_TICK_REG, REGION_REG, TRACK_REG,
+    },
+    region::Region,
+    state::{StateError, StateReader, StateWriter},
+};
+
+use super::{fds::audio::FDSAudio, Mapper, MapperError};
+
+/// Size of a bank
+const BANK_SIZE: usize = usize::pow(2, 12);
+
+/// 8 KiB RAM at 0x6000-0x7FFF
+const RAM_SIZE: usize = usize::pow(2, 13);
+
+/// Number of 4 KiB windows between 0x6000 and 0xFFFF
+const WINDOW_COUNT: usize = 10;
+
+/// Names of the expansion chips in the order of the header bits
+const CHIP_NAMES: [(u8, &str); 6] = [
+    (CHIP_VRC6, "VRC6"),
+    (CHIP_VRC7, "VRC7"),
+    (CHIP_FDS, "FDS"),
+    (CHIP_MMC5, "MMC5"),
+    (CHIP_N163, "Namco 163"),
+    (CHIP_S5B, "Sunsoft 5B"),
+];
+
+/// Offset of the loop waiting for play ticks in the driver
+const PLAY_LOOP_OFFSET: u16 = 0x3E;
+
+/// Offset of the RTI used as the NMI and IRQ handler in the driver
+const RTI_OFFSET: u16 = 0x4C;
+
+/// Builds the player code that clears the memory, calls INIT once and then calls PLAY on every tick
+fn build_driver(init: u16, play: u16) -> Vec<u8> {
+    let [init_low, init_high] = init.to_le_bytes();
+    let [play_low, play_high] = play.to_le_bytes();
+    let [tick_low, tick_high] = PLAY_TICK_REG.to_le_bytes();
+    let [track_low, track_high] = TRACK_REG.to_le_bytes();
+    let [region_low, region_high] = REGION_REG.to_le_bytes();
+    let [loop_low, loop_high] = (DRIVER_ADDRESS + PLAY_LOOP_OFFSET).to_le_bytes();
+
+    vec![
+        0x78, // sei
+        0xD8, // cld
+        0xA2,
+        0xFF, // ldx #$FF
+        0x9A, // txs
+        0xA9,
+        0x00, // lda #$00
+        0xAA, // tax
+        // clear the internal RAM
+        0x9D,
+        0x00,
+        0x00, // sta $0000,x
+        0x9D,
+        0x00,
+        0x01, // sta $0100,x
+        0x9D,
+        0x00,
+        0x02, // sta $0200,x
+        0x9D,
+        0x00,
+        0x03, // sta $0300,x
+        0x9D,
+        0x00,
+        0x04, // sta $0400,x
+        0x9D,
+        0x00,
+        0x05, // sta $0500,x
+        0x9D,
+        0x00,
+        0x06, // sta $0600,x
+        0x9D,
+        0x00,
+        0x07, // sta $0700,x
+        0xE8, // inx
+        0xD0,
+        0xE5, // bne $-27
+        // silence the APU
+        0xA2,
+        0x13, // ldx #$13
+        0x9D,
+        0x00,
+        0x40, // sta $4000,x
+        0xCA, // dex
+        0x10,
+        0xFA, // bpl $-6
+        0xA9,
+        0x0F, // lda #$0F
+        0x8D,
+        0x15,
+        0x40, // sta $4015
+        0xA9,
+        0x40, // lda #$40
+        0x8D,
+        0x17,
+        0x40, // sta $4017
+        // call INIT with the track in A and the region in X
+        0xAD,
+        track_low,
+        track_high, // lda TRACK_REG
+        0xAE,
+        region_low,
+        region_high, // ldx REGION_REG
+        0x20,
+        init_low,
+        init_high, // jsr INIT
+        // wait for the next tick and call PLAY
+        0xAD,
+        tick_low,
+        tick_high, // lda PLAY_TICK_REG
+        0xF0,
+        0xFB, // beq $-5
+        0x8D,
+        tick_low,
+        tick_high, // sta PLAY_TICK_REG
+        0x20,
+        play_low,
+        play_high, // jsr PLAY
+        0x4C,
+        loop_low,
+        loop_high, // jmp PLAY_LOOP
+        // NMI and IRQ handler
+        0x40, // rti
+    ]
+}
+
+// https://www.nesdev.org/wiki/NSF
+pub struct NSFMapper {
+    info: NSFInfo,
+    driver: Vec<u8>,
+
+    /// Program data padded so that it can be addressed in 4 KiB banks
+    original_data: Vec<u8>,
+    data: Vec<u8>,
+    /// Bank mapped to each 4 KiB window between 0x6000 and 0xFFFF
+    banks: [usize; WINDOW_COUNT],
+    ram: Vec<u8>,
+
+    track: u8,
+    region: Region,
+    play_period: u64,
+    play_counter: u64,
+    play_pending: bool,
+
+    /// FDS tunes can bankswitch 0x6000-0x7FFF and write to every bank
+    fds: Option<FDSAudio>,
+}
+
+impl NSFMapper {
+    /// Address the data is mapped from
+    fn base_address(&self) -> usize {
+        if self.fds.is_some() {
+            0x6000
+        } else {
+            0x8000
+        }
+    }
+
+    /// Restores the banks and memory to their state before INIT
+    fn restore_initial_state(&mut self) {
+        let first_window = (self.base_address() - 0x6000) / BANK_SIZE;
+        let bank_count = self.data.len() / BANK_SIZE;
+
+        self.data.copy_from_slice(&self.original_data);
+        self.ram.fill(0);
+
+        if self.info.uses_bankswitching() {
+            self.banks[2..].copy_from_slice(&self.info.bank_init.map(|bank| bank as usize));
+            if self.fds.is_some() {
+                self.banks[0] = self.info.bank_init[6] as usize;
+                self.banks[1] = self.info.bank_init[7] as usize;
+            }
+        } else {
+            for (idx, bank) in self.banks.iter_mut().enumerate() {
+                *bank = idx.saturating_sub(first_window);
+            }
+        }
+
+        for bank in self.banks.iter_mut() {
+            *bank %= bank_count;
+        }
+
+        self.play_counter = 0;
+        self.play_pending = false;
+    }
+
+    /// Offset into the data for an address in 0x6000-0xFFFF
+    fn data_offset(&self, addr: u16) -> usize {
+        let window = (addr as usize - 0x6000) / BANK_SIZE;
+        self.banks[window] * BANK_SIZE + (addr as usize & (BANK_SIZE - 1))
+    }
+}
+
+impl Mapper for NSFMapper {
+    fn new(nes_file: &NESFile) -> Self {
+        let info = nes_file.nsf.clone().unwrap();
+
+        let has_fds = info.chips & CHIP_FDS > 0;
+        for (chip, name) in CHIP_NAMES {
+            if chip != CHIP_FDS && info.chips & chip > 0 {
+                eprintln!("{} expansion audio is not supported", name);
+            }
+        }
+
+        let base_address = if has_fds { 0x6000 } else { 0x8000 };
+        let padding = if info.uses_bankswitching() {
+            info.load_address as usize & (BANK_SIZE - 1)
+        } else {
+            (info.load_address as usize).saturating_sub(base_address)
+        };
+
+        let mut data = vec![0; padding];
+        data.extend_from_slice(&nes_file.prg_rom);
+        let data_size = data
+            .len()
+            .next_multiple_of(BANK_SIZE)
+            .max(0x10000 - base_address);
+        data.resize(data_size, 0);
+
+        let region = nes_file.region.unwrap_or_default();
+        let speed = match region {
+            Region::Ntsc => info.ntsc_speed,
+            Region::Pal | Region::Dendy => info.pal_speed,
+        };
+        let play_period = (speed as f64 * region.cpu_clock_rate() / 1_000_000.0) as u64;
+
+        let mut mapper = Self {
+            driver: build_driver(info.init_address, info.play_address),
+            original_data: data.clone(),
+            data,
+            banks: [0; WINDOW_COUNT],
+            ram: vec![0; RAM_SIZE],
+            track: info.start_track,
+            region,
+            play_period,
+            play_counter: 0,
+            play_pending: false,
+            fds: has_fds.then(FDSAudio::new),
+            info,
+        };
+
+        mapper.restore_initial_state();
+        mapper
+    }
+
+    fn read_cpu(&mut self, addr: u16) -> Result<u8, MapperError> {
+        // none of the registers have read side effects
+        self.peek_cpu(addr)
+    }
+
+    fn peek_cpu(&self, addr: u16) -> Result<u8, MapperError> {
+        let driver_end = DRIVER_ADDRESS + self.driver.len() as u16;
+
+        match addr {
+            _ if (DRIVER_ADDRESS..driver_end).contains(&addr) => {
+                Ok(self.driver[(addr - DRIVER_ADDRESS) as usize])
+            }
+            PLAY_TICK_REG => Ok(self.play_pending.into()),
+            TRACK_REG => Ok(self.track),
+            // Dendy uses NTSC tuning
+            REGION_REG => Ok((self.region == Region::Pal).into()),
+            0x4040..=0x4097 if self.fds.is_some() => Ok(self.fds.as_ref().unwrap().read(addr)),
+            // the vectors point into the driver
+            0xFFFA | 0xFFFE => Ok((DRIVER_ADDRESS + RTI_OFFSET) as u8),
+            0xFFFB | 0xFFFF => Ok(((DRIVER_ADDRESS + RTI_OFFSET) >> 8) as u8),
+            0xFFFC => Ok(DRIVER_ADDRESS as u8),
+            0xFFFD => Ok((DRIVER_ADDRESS >> 8) as u8),
+            0x6000..=0x7FFF if self.fds.is_none() => Ok(self.ram[addr as usize - 0x6000]),
+            0x6000..=0xFFFF => Ok(self.data[self.data_offset(addr)]),
+            _ => Ok(0),
+        }
+    }
+
+    fn write_cpu(&mut self, addr: u16, val: u8) -> Result<(), MapperError> {
+        let bank_count = self.data.len() / BANK_SIZE;
+
+        match addr {
+            PLAY_TICK_REG => self.play_pending = false,
+            TRACK_REG => {
+                self.track = val;
+                self.restore_initial_state();
+            }
+            0x4040..=0x408A => {
+                if let Some(fds) = &mut self.fds {
+                    fds.write(addr, val);
+                }
+            }
+            0x5FF6..=0x5FF7 if self.fds.is_some() => {
+                self.banks[(addr - 0x5FF6) as usize] = val as usize % bank_count;
+            }
+            0x5FF8..=0x5FFF => {
+                self.banks[(addr - 0x5FF8) as usize + 2] = val as usize % bank_count;
+            }
+            0x6000..=0x7FFF if self.fds.is_none() => self.ram[addr as usize - 0x6000] = val,
+            // FDS tunes are loaded into RAM
+            0x6000..=0xFFFF if self.fds.is_some() => {
+                let off = self.data_offset(addr);
+                self.data[off] = val;
+            }
+            // registers of the expansion audio chips other than the FDS
+            _ if self.info.chips & !CHIP_FDS > 0 => {
+                return Err(MapperError::Unsupported("expansion audio"))
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn read_ppu(&self, _addr: u16) -> Result<u8, MapperError> {
+        Ok(0)
+    }
+
+    fn write_ppu(&mut self, _addr: u16, _val: u8) -> Result<(), MapperError> {
+        Ok(())
+    }
+
+    fn entrypoint(&self) -> u16 {
+        DRIVER_ADDRESS
+    }
+
+    fn power_cycle(&mut self) {
+        self.restore_initial_state();
+    }
+
+    fn mirroring_mode(&self) -> MirroringMode {
+        MirroringMode::Horizontal
+    }
+
+    fn save_state(&self, writer: &mut StateWriter) {
+        for bank in self.banks {
+            writer.write_usize(bank);
+        }
+        writer.write_bytes(&self.ram);
+
+        writer.write_u8(self.track);
+        writer.write_u64(self.play_counter);
+        writer.write_bool(self.play_pending);
+
+        if let Some(fds) = &self.fds {
+            // the data is only writable on the FDS
+            writer.write_bytes(&self.data);
+            fds.save_state(writer);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), StateError> {
+        let bank_count = self.data.len() / BANK_SIZE;
+        for bank in self.banks.iter_mut() {
+            *bank = reader.read_usize()?;
+            if *bank >= bank_count {
+                return Err(StateError::Corrupted);
+            }
+        }
+        reader.read_into(&mut self.ram)?;
+
+        self.track = reader.read_u8()?;
+        self.play_counter = reader.read_u64()?;
+        self.play_pending = reader.read_bool()?;
+
+        if let Some(fds) = &mut self.fds {
+            reader.read_into(&mut self.data)?;
+            fds.load_state(reader)?;
+        }
+
+        Ok(())
+    }
+
+    fn clock_cpu(&mut self) {
+        self.play_counter += 1;
+        if self.play_counter >= self.play_period {
+            self.play_counter = 0;
+            self.play_pending = true;
+        }
+
+        if let Some(fds) = &mut self.fds {
+            fds.clock();
+        }
+    }
+
+    fn expansion_audio(&self) -> f32 {
+        self.fds.as_ref().map_or(0.0, |fds| fds.output())
+    }
+}
@@ -0,0 +1,547 @@
+use std::{
+    fmt, fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    checksum::crc32,
+    region::Region,
+    state::{StateError, StateReader, StateWriter},
+};
+
+const MOVIE_MAGIC: [u8; 4] = *b"BRMV";
+
+/// Incremented whenever the layout of the movie changes
+const MOVIE_VERSION: u32 = 2;
+
+/// Extension of movies stored in the FCEUX text format
+const FM2_EXTENSION: &str = "fm2";
+
+/// FM2 version written and accepted
+const FM2_VERSION: u32 = 3;
+
+/// Button letters of an FM2 controller field, from the highest bit to the lowest
+const FM2_BUTTONS: &[u8; 8] = b"RLDUTSBA";
+
+/// Frame commands, using the FM2 bit assignments
+pub const COMMAND_SOFT_RESET: u8 = 1 << 0;
+pub const COMMAND_POWER: u8 = 1 << 1;
+pub const COMMAND_FDS_SELECT: u8 = 1 << 3;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug)]
+pub enum MovieError {
+    /// The movie is neither a native movie nor a valid FM2 file
+    InvalidFormat,
+
+    /// The movie was recorded by an incompatible version
+    UnsupportedVersion(u32),
+
+    /// The movie uses a feature the emulator can't reproduce
+    Unsupported(&'static str),
+
+    /// The save state the movie starts from can't be loaded
+    StartState(StateError),
+
+    /// The movie file couldn't be read or written
+    Io(io::Error),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::InvalidFormat => write!(f, "not a movie"),
+            MovieError::UnsupportedVersion(version) => {
+                write!(f, "unsupported movie version {}", version)
+            }
+            MovieError::Unsupported(feature) => write!(f, "unsupported movie feature: {}", feature),
+            MovieError::StartState(err) => write!(f, "could not load the starting state: {}", err),
+            MovieError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<StateError> for MovieError {
+    fn from(_: StateError) -> MovieError {
+        MovieError::InvalidFormat
+    }
+}
+
+/// Where the playback begins
+pub enum MovieStart {
+    PowerOn,
+    /// Save state taken when the recording started
+    SaveState(Vec<u8>),
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct MovieFrame {
+    /// COMMAND_* bits executed before the frame
+    pub command: u8,
+    /// Buttons held on both controllers
+    pub buttons: [u8; 2],
+}
+
+pub struct Movie {
+    pub start: MovieStart,
+    /// MD5 of the ROM contents the movie was recorded with
+    pub rom_md5: Option<[u8; 16]>,
+    /// Name of the ROM file without the extension, only kept for FM2
+    pub rom_name: String,
+    /// Timing the movie was recorded with, the frames desync under any other
+    pub region: Region,
+    pub frames: Vec<MovieFrame>,
+}
+
+impl Movie {
+    pub fn new(start: MovieStart, rom_md5: [u8; 16], rom_name: String, region: Region) -> Movie {
+        Movie {
+            start,
+            rom_md5: Some(rom_md5),
+            rom_name,
+            region,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Loads a native or FM2 movie
+    pub fn load(path: &Path) -> Result<Movie, MovieError> {
+        let data = fs::read(path).map_err(MovieError::Io)?;
+
+        if data.starts_with(&MOVIE_MAGIC) {
+            Movie::from_bytes(&data)
+        } else {
+            let text = String::from_utf8(data).map_err(|_| MovieError::InvalidFormat)?;
+            Movie::from_fm2(&text)
+        }
+    }
+
+    /// Stores the movie, as FM2 when the path has the .fm2 extension
+    pub fn save(&self, path: &Path) -> Result<(), MovieError> {
+        let data = if path.extension().is_some_and(|ext| ext == FM2_EXTENSION) {
+            self.to_fm2()?.into_bytes()
+        } else {
+            self.to_bytes()
+        };
+
+        fs::write(path, data).map_err(MovieError::Io)
+    }
+
+    /// [magic][version: u32][md5][region: u8][start state blob, empty on power on]
+    /// [frame count][frames...]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        writer.write_bytes(&MOVIE_MAGIC);
+        writer.write_u32(MOVIE_VERSION);
+        writer.write_bytes(&self.rom_md5.unwrap_or_default());
+        writer.write_u8(match self.region {
+            Region::Ntsc => 0,
+            Region::Pal => 1,
+            Region::Dendy => 2,
+        });
+
+        match &self.start {
+            MovieStart::PowerOn => writer.write_blob(&[]),
+            MovieStart::SaveState(state) => writer.write_blob(state),
+        }
+
+        writer.write_usize(self.frames.len());
+        for frame in &self.frames {
+            writer.write_u8(frame.command);
+            writer.write_bytes(&frame.buttons);
+        }
+
+        writer.into_bytes()
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Movie, MovieError> {
+        let mut reader = StateReader::new(data);
+        if reader.read_bytes(MOVIE_MAGIC.len())? != MOVIE_MAGIC {
+            return Err(MovieError::InvalidFormat);
+        }
+
+        let version = reader.read_u32()?;
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let mut rom_md5 = [0; 16];
+        reader.read_into(&mut rom_md5)?;
+
+        let region = match reader.read_u8()? {
+            0 => Region::Ntsc,
+            1 => Region::Pal,
+            2 => Region::Dendy,
+            _ => return Err(MovieError::InvalidFormat),
+        };
+
+        let state = reader.read_blob()?;
+        let start = if state.is_empty() {
+            MovieStart::PowerOn
+        } else {
+            MovieStart::SaveState(state.to_vec())
+        };
+
+        let frame_count = reader.read_usize()?;
+        let mut frames = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            let command = reader.read_u8()?;
+            let mut buttons = [0; 2];
+            reader.read_into(&mut buttons)?;
+            frames.push(MovieFrame { command, buttons });
+        }
+
+        Ok(Movie {
+            start,
+            rom_md5: Some(rom_md5),
+            rom_name: String::new(),
+            region,
+            frames,
+        })
+    }
+
+    /// FCEUX text movie
+    /// https://fceux.com/web/FM2.html
+    fn from_fm2(text: &str) -> Result<Movie, MovieError> {
+        let mut version = None;
+        let mut rom_md5 = None;
+        let mut rom_name = String::new();
+        let mut region = Region::Ntsc;
+        let mut ports = [1, 1, 0];
+        let mut frames = Vec::new();
+
+        for line in text.lines() {
+            if let Some(record) = line.strip_prefix('|') {
+                frames.push(parse_fm2_frame(record, &ports)?);
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key {
+                "version" => version = value.parse().ok(),
+                "palFlag" => {
+                    region = match value {
+                        "0" => Region::Ntsc,
+                        "1" => Region::Pal,
+                        _ => return Err(MovieError::InvalidFormat),
+                    }
+                }
+                "romFilename" => rom_name = value.to_string(),
+                "romChecksum" => {
+                    let digest = value
+                        .strip_prefix("base64:")
+                        .and_then(base64_decode)
+                        .ok_or(MovieError::InvalidFormat)?;
+                    rom_md5 = digest.try_into().ok();
+                }
+                "port0" | "port1" | "port2" => {
+                    let port = (key.as_bytes()[4] - b'0') as usize;
+                    ports[port] = value.parse().map_err(|_| MovieError::InvalidFormat)?;
+                }
+                "binary" if value != "0" => return Err(MovieError::Unsupported("binary FM2")),
+                "fourscore" if value != "0" => return Err(MovieError::Unsupported("Four Score")),
+                "savestate" => {
+                    return Err(MovieError::Unsupported(
+                        "FM2 movies starting from a save state",
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        match version {
+            Some(FM2_VERSION) => {}
+            Some(version) => return Err(MovieError::UnsupportedVersion(version)),
+            None => return Err(MovieError::InvalidFormat),
+        }
+
+        // Gamepads(1) and empty ports(0) are the only devices supported
+        if ports[..2].iter().any(|&port| port > 1) || ports[2] != 0 {
+            return Err(MovieError::Unsupported("input devices other than gamepads"));
+        }
+
+        Ok(Movie {
+            start: MovieStart::PowerOn,
+            rom_md5,
+            rom_name,
+            region,
+            frames,
+        })
+    }
+
+    fn to_fm2(&self) -> Result<String, MovieError> {
+        if let MovieStart::SaveState(_) = self.start {
+            return Err(MovieError::Unsupported(
+                "FM2 movies starting from a save state",
+            ));
+        }
+
+        let pal_flag = match self.region {
+            Region::Ntsc => 0,
+            Region::Pal => 1,
+            Region::Dendy => return Err(MovieError::Unsupported("Dendy timing in FM2")),
+        };
+
+        let mut text = String::new();
+        text += &format!("version {}\n", FM2_VERSION);
+        text += "emuVersion 22020\n";
+        text += "rerecordCount 0\n";
+        text += &format!("palFlag {}\n", pal_flag);
+        text += &format!("romFilename {}\n", self.rom_name);
+        text += &format!(
+            "romChecksum base64:{}\n",
+            base64_encode(&self.rom_md5.unwrap_or_default())
+        );
+        text += &format!("guid {}\n", generate_guid());
+        text += "fourscore 0\n";
+        text += "microphone 0\n";
+        text += "port0 1\n";
+        text += "port1 1\n";
+        text += "port2 0\n";
+        text += "FDS 0\n";
+        text += "NewPPU 0\n";
+
+        for frame in &self.frames {
+            text += &format!(
+                "|{}|{}|{}||\n",
+                frame.command,
+                format_fm2_buttons(frame.buttons[0]),
+                format_fm2_buttons(frame.buttons[1])
+            );
+        }
+
+        Ok(text)
+    }
+}
+
+/// Parses "c|RLDUTSBA|RLDUTSBA||", the leading '|' already removed
+fn parse_fm2_frame(record: &str, ports: &[u8; 3]) -> Result<MovieFrame, MovieError> {
+    let mut fields = record.split('|');
+
+    let command = fields
+        .next()
+        .and_then(|command| command.trim().parse().ok())
+        .ok_or(MovieError::InvalidFormat)?;
+
+    let mut buttons = [0; 2];
+    for (port, buttons) in buttons.iter_mut().enumerate() {
+        let field = fields.next().ok_or(MovieError::InvalidFormat)?;
+        if ports[port] == 0 {
+            continue;
+        }
+
+        if field.len() != FM2_BUTTONS.len() {
+            return Err(MovieError::InvalidFormat);
+        }
+
+        // Anything other than a space or a dot means the button is held
+        *buttons = field.bytes().fold(0, |buttons, c| {
+            buttons << 1 | (c != b' ' && c != b'.') as u8
+        });
+    }
+
+    Ok(MovieFrame { command, buttons })
+}
+
+fn format_fm2_buttons(buttons: u8) -> String {
+    FM2_BUTTONS
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            if buttons & (0x80 >> i) > 0 {
+                c as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// Random-enough GUID identifying the recording
+fn generate_guid() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+        .to_le_bytes();
+
+    let bytes: Vec<u8> = (0..4u8)
+        .flat_map(|i| crc32(&[&nanos[..], &[i]].concat()).to_le_bytes())
+        .collect();
+    let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::new();
+
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (byte as u32) << (16 - 8 * i)
+        });
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut bits = 0u32;
+    let mut bit_count = 0;
+
+    for c in text.bytes().take_while(|&c| c != b'=') {
+        let value = BASE64_ALPHABET.iter().position(|&a| a == c)? as u32;
+        bits = (bits << 6 | value) & 0xFFFF;
+        bit_count += 6;
+
+        if bit_count >= 8 {
+            bit_count -= 8;
+            out.push((bits >> bit_count) as u8);
+        }
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::region::Region;
+
+    use super::{
+        base64_decode, base64_encode, Movie, MovieError, MovieFrame, MovieStart, COMMAND_SOFT_RESET,
+    };
+
+    const MD5: [u8; 16] = [
+        0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF, 0xFE, 0xDC, 0xBA, 0x98, 0x76, 0x54, 0x32,
+        0x10,
+    ];
+
+    fn movie(region: Region) -> Movie {
+        let mut movie = Movie::new(MovieStart::PowerOn, MD5, "game".to_string(), region);
+        movie.frames = vec![
+            MovieFrame {
+                command: COMMAND_SOFT_RESET,
+                buttons: [0, 0],
+            },
+            MovieFrame {
+                command: 0,
+                buttons: [0x81, 0x42],
+            },
+        ];
+        movie
+    }
+
+    /// Frames don't implement PartialEq, compare their fields
+    fn frames(movie: &Movie) -> Vec<(u8, [u8; 2])> {
+        movie
+            .frames
+            .iter()
+            .map(|frame| (frame.command, frame.buttons))
+            .collect()
+    }
+
+    #[test]
+    fn fm2_round_trip() {
+        let original = movie(Region::Pal);
+
+        let text = original.to_fm2().unwrap();
+        let parsed = Movie::from_fm2(&text).unwrap();
+
+        assert!(text.contains("palFlag 1\n"));
+        assert!(text.contains("|0|R......A|.L....B.||\n"));
+        assert_eq!(parsed.rom_md5, Some(MD5));
+        assert_eq!(parsed.rom_name, "game");
+        assert_eq!(parsed.region, Region::Pal);
+        assert_eq!(frames(&parsed), frames(&original));
+    }
+
+    #[test]
+    fn parses_fceux_button_fields() {
+        let text = "version 3\nport0 1\nport1 0\nport2 0\n\
+            |0|RLDUTSBA|........||\n\
+            |2|   U   A|RLDUTSBA||\n";
+
+        let parsed = Movie::from_fm2(text).unwrap();
+
+        assert_eq!(parsed.region, Region::Ntsc);
+        assert_eq!(parsed.rom_md5, None);
+        // the second port is empty so its field is ignored
+        assert_eq!(frames(&parsed), [(0, [0xFF, 0]), (2, [0x11, 0])]);
+    }
+
+    #[test]
+    fn rejects_unsupported_fm2_movies() {
+        let unsupported = [
+            "version 3\nfourscore 1\n",
+            "version 3\nbinary 1\n",
+            "version 3\nsavestate base64:AAAA\n",
+            "version 3\nport2 1\n",
+        ];
+        for text in unsupported {
+            assert!(
+                matches!(Movie::from_fm2(text), Err(MovieError::Unsupported(_))),
+                "{}",
+                text
+            );
+        }
+
+        assert!(matches!(
+            Movie::from_fm2("version 2\n"),
+            Err(MovieError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            Movie::from_fm2("|0|........|........||\n"),
+            Err(MovieError::InvalidFormat)
+        ));
+        assert!(matches!(
+            movie(Region::Dendy).to_fm2(),
+            Err(MovieError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn native_round_trip() {
+        let original = movie(Region::Dendy);
+
+        let parsed = Movie::from_bytes(&original.to_bytes()).unwrap();
+
+        assert_eq!(parsed.rom_md5, Some(MD5));
+        assert_eq!(parsed.region, Region::Dendy);
+        assert!(matches!(parsed.start, MovieStart::PowerOn));
+        assert_eq!(frames(&parsed), frames(&original));
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        for len in 0..8 {
+            let data: Vec<u8> = (0..len).map(|i| i * 37).collect();
+            assert_eq!(base64_decode(&base64_encode(&data)).unwrap(), data);
+        }
+
+        assert_eq!(base64_encode(b"Man"), "TWFu");
+        assert_eq!(base64_encode(b"Ma"), "TWE=");
+    }
+}
@@ -3,8 +3,22 @@ use modular_bitfield::{
     specifiers::{B1, B4},
 };
 
+use crate::{nsf::NSFInfo, region::Region};
+
 const NES_MAGIC: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
 
+/// Size of the iNES header
+const HEADER_SIZE: usize = 16;
+
+/// Size of the trainer
+const TRAINER_SIZE: usize = 512;
+
+/// PRG ROM bank size(16 KiB)
+pub const PRG_ROM_UNIT: usize = usize::pow(2, 14);
+
+/// CHR ROM bank size(8 KiB)
+pub const CHR_ROM_UNIT: usize = usize::pow(2, 13);
+
 #[bitfield]
 struct Flags6 {
     vertical_mirroring: B1,
@@ -14,17 +28,16 @@ struct Flags6 {
     ignore: B4,
 }
 
+#[derive(Clone, Copy)]
 pub enum MirroringMode {
     Horizontal,
     Vertical,
+    SingleScreenLower,
+    SingleScreenUpper,
+    FourScreen,
 }
 
 pub struct NESFile {
-    /// in 16 KiB units
-    pub prg_rom_size: u8,
-    /// in 16 KiB units
-    pub chr_rom_size: u8,
-
     ///
     pub mirroring_mode: MirroringMode,
 
@@ -36,9 +49,28 @@ pub struct NESFile {
 
     /// Mapper number
     pub mapper_number: u8,
+
+    /// PRG ROM contents
+    pub prg_rom: Vec<u8>,
+
+    /// CHR ROM contents
+    pub chr_rom: Vec<u8>,
+
+    /// FDS disk sides, empty for cartridges
+    pub disk_sides: Vec<Vec<u8>>,
+
+    /// NSF tune information, only present for NSF files
+    pub nsf: Option<NSFInfo>,
+
+    /// Region the file is meant for, None if the file doesn't specify it
+    pub region: Option<Region>,
 }
 
 pub fn parse_nes_file(file: &[u8]) -> Result<NESFile, ()> {
+    if file.len() < HEADER_SIZE {
+        return Err(());
+    }
+
     let magic = &file[..4];
     if magic != NES_MAGIC {
         return Err(());
@@ -52,17 +84,48 @@ pub fn parse_nes_file(file: &[u8]) -> Result<NESFile, ()> {
     let _flags_9 = file[9];
     let _flags_10 = file[10];
 
+    // the timing byte is only present in NES 2.0 headers
+    let is_nes2 = flags_7 & 0b1100 == 0b1000;
+    let region = if is_nes2 {
+        match file[12] & 0b11 {
+            1 => Some(Region::Pal),
+            3 => Some(Region::Dendy),
+            // NTSC or multi-region
+            _ => Some(Region::Ntsc),
+        }
+    } else {
+        None
+    };
+
+    let has_trainer = flags_6.trainer() > 0;
+
+    let prg_rom_start = HEADER_SIZE + if has_trainer { TRAINER_SIZE } else { 0 };
+    let prg_rom_end = prg_rom_start + prg_rom_size as usize * PRG_ROM_UNIT;
+
+    let chr_rom_start = prg_rom_end;
+    let chr_rom_end = chr_rom_start + chr_rom_size as usize * CHR_ROM_UNIT;
+
+    let prg_rom = Vec::from(file.get(prg_rom_start..prg_rom_end).ok_or(())?);
+    let chr_rom = Vec::from(file.get(chr_rom_start..chr_rom_end).ok_or(())?);
+
+    let mirroring_mode = if flags_6.ignore_mirroring_control() > 0 {
+        MirroringMode::FourScreen
+    } else if flags_6.vertical_mirroring() > 0 {
+        MirroringMode::Vertical
+    } else {
+        MirroringMode::Horizontal
+    };
+
     let nes = NESFile {
-        prg_rom_size,
-        chr_rom_size,
-        mirroring_mode: if flags_6.vertical_mirroring() > 0 {
-            MirroringMode::Vertical
-        } else {
-            MirroringMode::Horizontal
-        },
+        mirroring_mode,
         has_prg_ram: flags_6.prg_ram() > 0,
-        has_trainer: flags_6.trainer() > 0,
+        has_trainer,
         mapper_number: flags_6.into_bytes()[0] >> 4 | flags_7 & 0b11110000,
+        prg_rom,
+        chr_rom,
+        disk_sides: Vec::new(),
+        nsf: None,
+        region,
     };
 
     Ok(nes)
@@ -0,0 +1,55 @@
+use crate::emu::Emulator;
+
+/// Columns holding the address and the instruction bytes
+const BYTES_END: usize = 14;
+
+/// Start of the register columns, the disassembly before them differs between emulators
+const REGISTERS_START: usize = 48;
+
+/// First instruction that differs from the reference log
+pub struct Divergence {
+    /// 1-based line of the reference log
+    pub line: usize,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// The parts of a trace line that are compared, the disassembly is skipped
+fn comparable(line: &str) -> Option<(&str, &str)> {
+    Some((
+        line.get(..BYTES_END)?,
+        line.get(REGISTERS_START..)?.trim_end(),
+    ))
+}
+
+/// Runs nestest.nes from $C000 in its automated mode and diffs the trace against nestest.log
+/// https://www.qmtpro.com/~nes/misc/nestest.txt
+/// Returns the number of matching instructions
+pub fn run_nestest(emu: &mut Emulator, log: &str) -> Result<usize, Divergence> {
+    emu.jump_to_entrypoint();
+    emu.enable_trace();
+
+    let mut matched = 0;
+    for (idx, expected) in log.lines().enumerate() {
+        if expected.trim().is_empty() {
+            continue;
+        }
+
+        let actual = emu.next_trace_line();
+
+        match (comparable(expected), comparable(&actual)) {
+            (Some(expected_fields), Some(actual_fields)) if expected_fields == actual_fields => {
+                matched += 1;
+            }
+            _ => {
+                return Err(Divergence {
+                    line: idx + 1,
+                    expected: expected.to_string(),
+                    actual,
+                })
+            }
+        }
+    }
+
+    Ok(matched)
+}
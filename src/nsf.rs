@@ -0,0 +1,282 @@
+use crate::{
+    nes::{MirroringMode, NESFile},
+    region::Region,
+};
+
+const NSF_MAGIC: [u8; 5] = *b"NESM\x1A";
+const NSFE_MAGIC: [u8; 4] = *b"NSFE";
+
+/// Size of the NSF header
+const HEADER_SIZE: usize = 0x80;
+
+/// Mapper number used internally for the NSF player, not assigned to any board
+pub const NSF_MAPPER_NUMBER: u8 = 255;
+
+/// Address of the player code that calls INIT and PLAY
+pub const DRIVER_ADDRESS: u16 = 0x4100;
+
+/// Reading returns 1 when PLAY has to be called, writing acknowledges it
+pub const PLAY_TICK_REG: u16 = 0x4180;
+
+/// Current track(0-based), writing selects a new track and restores the initial banks
+pub const TRACK_REG: u16 = 0x4181;
+
+/// 0 - NTSC, 1 - PAL
+pub const REGION_REG: u16 = 0x4182;
+
+/// Default NTSC play rate in microseconds
+const DEFAULT_NTSC_SPEED: u16 = 16639;
+
+/// Default PAL play rate in microseconds
+const DEFAULT_PAL_SPEED: u16 = 19997;
+
+/// Region flags of a PAL only tune, bit 1 marks tunes supporting both regions
+const PAL_ONLY: u8 = 0b01;
+
+pub const CHIP_VRC6: u8 = 1 << 0;
+pub const CHIP_VRC7: u8 = 1 << 1;
+pub const CHIP_FDS: u8 = 1 << 2;
+pub const CHIP_MMC5: u8 = 1 << 3;
+pub const CHIP_N163: u8 = 1 << 4;
+pub const CHIP_S5B: u8 = 1 << 5;
+
+#[derive(Clone, Default)]
+pub struct NSFTrack {
+    /// Track title from the tlbl chunk
+    pub name: Option<String>,
+
+    /// Track length in milliseconds from the time chunk
+    pub length: Option<u32>,
+
+    /// Fade out length in milliseconds from the fade chunk
+    pub fade: Option<u32>,
+}
+
+#[derive(Clone)]
+pub struct NSFInfo {
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+
+    /// Play rate in microseconds on NTSC
+    pub ntsc_speed: u16,
+
+    /// Play rate in microseconds on PAL
+    pub pal_speed: u16,
+
+    /// The tune only supports PAL systems
+    pub pal: bool,
+
+    /// Initial values of $5FF8-$5FFF, all zero if bankswitching isn't used
+    pub bank_init: [u8; 8],
+
+    /// Expansion audio chips used
+    pub chips: u8,
+
+    /// First track to play(0-based)
+    pub start_track: u8,
+
+    pub title: String,
+    pub artist: String,
+    pub copyright: String,
+
+    pub tracks: Vec<NSFTrack>,
+}
+
+impl NSFInfo {
+    pub fn uses_bankswitching(&self) -> bool {
+        self.bank_init.iter().any(|&bank| bank != 0)
+    }
+}
+
+/// Returns whether the file is an NSF or NSFe file
+pub fn is_nsf_file(file: &[u8]) -> bool {
+    file.starts_with(&NSF_MAGIC) || file.starts_with(&NSFE_MAGIC)
+}
+
+fn read_u16(data: &[u8], off: usize) -> Result<u16, ()> {
+    let bytes = data.get(off..off + 2).ok_or(())?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads null-terminated strings
+fn read_strings(data: &[u8]) -> Vec<String> {
+    data.split(|&c| c == 0)
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
+fn read_string(data: &[u8]) -> String {
+    read_strings(data).into_iter().next().unwrap_or_default()
+}
+
+/// Reads the per track times from the time and fade chunks, negative values mean the default
+fn read_times(data: &[u8]) -> Vec<Option<u32>> {
+    data.chunks_exact(4)
+        .map(|time| {
+            let time = i32::from_le_bytes(time.try_into().unwrap());
+            u32::try_from(time).ok()
+        })
+        .collect()
+}
+
+/// Parses NSFe chunks into `info`, returns the program data if a DATA chunk was found
+fn parse_chunks<'a>(chunks: &'a [u8], info: &mut NSFInfo) -> Result<Option<&'a [u8]>, ()> {
+    let mut program = None;
+    let mut names = Vec::new();
+    let mut lengths = Vec::new();
+    let mut fades = Vec::new();
+
+    let mut off = 0;
+    while off + 8 <= chunks.len() {
+        let len = u32::from_le_bytes(chunks[off..off + 4].try_into().unwrap()) as usize;
+        let id = &chunks[off + 4..off + 8];
+        let data = chunks.get(off + 8..off + 8 + len).ok_or(())?;
+        off += 8 + len;
+
+        match id {
+            b"INFO" => {
+                info.load_address = read_u16(data, 0)?;
+                info.init_address = read_u16(data, 2)?;
+                info.play_address = read_u16(data, 4)?;
+                info.pal = data.get(6).copied().unwrap_or(0) & 0b11 == PAL_ONLY;
+                info.chips = data.get(7).copied().unwrap_or(0);
+
+                let track_count = data.get(8).copied().unwrap_or(1);
+                info.tracks = vec![NSFTrack::default(); track_count as usize];
+                info.start_track = data.get(9).copied().unwrap_or(0);
+            }
+            b"DATA" => program = Some(data),
+            b"BANK" => {
+                info.bank_init = [0; 8];
+                let len = data.len().min(8);
+                info.bank_init[..len].copy_from_slice(&data[..len]);
+            }
+            b"RATE" => {
+                info.ntsc_speed = read_u16(data, 0)?;
+                if let Ok(speed) = read_u16(data, 2) {
+                    info.pal_speed = speed;
+                }
+            }
+            b"auth" => {
+                let mut strings = read_strings(data).into_iter();
+                info.title = strings.next().unwrap_or_default();
+                info.artist = strings.next().unwrap_or_default();
+                info.copyright = strings.next().unwrap_or_default();
+            }
+            b"tlbl" => names = read_strings(data),
+            b"time" => lengths = read_times(data),
+            b"fade" => fades = read_times(data),
+            b"NEND" => break,
+            _ => {}
+        }
+    }
+
+    for (idx, track) in info.tracks.iter_mut().enumerate() {
+        track.name = names.get(idx).filter(|name| !name.is_empty()).cloned();
+        track.length = lengths.get(idx).copied().flatten();
+        track.fade = fades.get(idx).copied().flatten();
+    }
+
+    Ok(program)
+}
+
+// https://www.nesdev.org/wiki/NSF
+fn parse_nsf(file: &[u8]) -> Result<(NSFInfo, &[u8]), ()> {
+    if file.len() < HEADER_SIZE {
+        return Err(());
+    }
+
+    let version = file[5];
+    let track_count = file[6];
+    let start_track = file[7].saturating_sub(1);
+
+    let mut bank_init = [0; 8];
+    bank_init.copy_from_slice(&file[0x70..0x78]);
+
+    let speed_or_default = |speed, default| if speed == 0 { default } else { speed };
+
+    let mut info = NSFInfo {
+        load_address: read_u16(file, 0x08)?,
+        init_address: read_u16(file, 0x0A)?,
+        play_address: read_u16(file, 0x0C)?,
+        ntsc_speed: speed_or_default(read_u16(file, 0x6E)?, DEFAULT_NTSC_SPEED),
+        pal_speed: speed_or_default(read_u16(file, 0x78)?, DEFAULT_PAL_SPEED),
+        pal: file[0x7A] & 0b11 == PAL_ONLY,
+        bank_init,
+        chips: file[0x7B],
+        start_track,
+        title: read_string(&file[0x0E..0x2E]),
+        artist: read_string(&file[0x2E..0x4E]),
+        copyright: read_string(&file[0x4E..0x6E]),
+        tracks: vec![NSFTrack::default(); track_count as usize],
+    };
+
+    // NSF2 can store NSFe metadata after the program
+    let program_len = u32::from_le_bytes([file[0x7D], file[0x7E], file[0x7F], 0]) as usize;
+    let program = if version >= 2 && program_len > 0 {
+        let program_end = HEADER_SIZE + program_len;
+        parse_chunks(file.get(program_end..).ok_or(())?, &mut info)?;
+        &file[HEADER_SIZE..program_end]
+    } else {
+        &file[HEADER_SIZE..]
+    };
+
+    Ok((info, program))
+}
+
+// https://www.nesdev.org/wiki/NSFe
+fn parse_nsfe(file: &[u8]) -> Result<(NSFInfo, &[u8]), ()> {
+    let mut info = NSFInfo {
+        load_address: 0,
+        init_address: 0,
+        play_address: 0,
+        ntsc_speed: DEFAULT_NTSC_SPEED,
+        pal_speed: DEFAULT_PAL_SPEED,
+        pal: false,
+        bank_init: [0; 8],
+        chips: 0,
+        start_track: 0,
+        title: String::new(),
+        artist: String::new(),
+        copyright: String::new(),
+        tracks: Vec::new(),
+    };
+
+    let program = parse_chunks(&file[NSFE_MAGIC.len()..], &mut info)?.ok_or(())?;
+    if info.tracks.is_empty() {
+        return Err(());
+    }
+
+    Ok((info, program))
+}
+
+pub fn parse_nsf_file(file: &[u8]) -> Result<NESFile, ()> {
+    let (info, program) = if file.starts_with(&NSF_MAGIC) {
+        parse_nsf(file)?
+    } else if file.starts_with(&NSFE_MAGIC) {
+        parse_nsfe(file)?
+    } else {
+        return Err(());
+    };
+
+    if info.tracks.is_empty() || program.is_empty() {
+        return Err(());
+    }
+
+    let region = if info.pal { Region::Pal } else { Region::Ntsc };
+
+    let nes = NESFile {
+        mirroring_mode: MirroringMode::Horizontal,
+        has_prg_ram: false,
+        has_trainer: false,
+        mapper_number: NSF_MAPPER_NUMBER,
+        prg_rom: Vec::from(program),
+        chr_rom: Vec::new(),
+        disk_sides: Vec::new(),
+        nsf: Some(info),
+        region: Some(region),
+    };
+
+    Ok(nes)
+}
@@ -0,0 +1,182 @@
+use crate::palette::{
+    gamma_correct, ntsc_demodulation_angle, ntsc_signal, yiq_to_linear, NtscParams, Palette,
+};
+
+/// Width of the filtered image, 7 output pixels for every 3 PPU pixels like blargg's nes_ntsc
+pub const NTSC_OUTPUT_WIDTH: usize = 602;
+
+/// Width and height of the PPU output
+const INPUT_WIDTH: usize = 256;
+const INPUT_HEIGHT: usize = 240;
+
+/// The PPU outputs 8 of the 12 subcarrier phases per dot
+/// https://www.nesdev.org/wiki/NTSC_video#Scanline_Timing
+const SAMPLES_PER_PIXEL: usize = 8;
+const SAMPLES_PER_LINE: usize = INPUT_WIDTH * SAMPLES_PER_PIXEL;
+
+/// Entries of the gamma lookup table, powf per channel is too slow for a whole frame
+const GAMMA_TABLE_SIZE: usize = 1024;
+
+/// 341 dots of 8 samples shift the subcarrier by 4 phases every scanline and every frame
+const PHASE_SHIFT: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NtscPreset {
+    /// Luma and chroma share a signal, which blurs colors and creates artifact colors
+    Composite,
+
+    /// Luma and chroma are separate, colors still bleed but don't crawl
+    SVideo,
+
+    /// Every dot is decoded on its own, only the output is resampled
+    Rgb,
+
+    /// Composite luma without chroma
+    Monochrome,
+}
+
+impl NtscPreset {
+    pub fn from_name(name: &str) -> Option<NtscPreset> {
+        match name.to_ascii_lowercase().as_str() {
+            "composite" => Some(NtscPreset::Composite),
+            "svideo" | "s-video" => Some(NtscPreset::SVideo),
+            "rgb" => Some(NtscPreset::Rgb),
+            "monochrome" => Some(NtscPreset::Monochrome),
+            _ => None,
+        }
+    }
+
+    /// Number of samples luma and chroma are averaged over, wider means blurrier
+    fn bandwidth(&self) -> (usize, usize) {
+        match self {
+            NtscPreset::Composite | NtscPreset::Monochrome => (12, 24),
+            NtscPreset::SVideo => (4, 24),
+            NtscPreset::Rgb => (1, 1),
+        }
+    }
+}
+
+/// Decodes the composite signal of the PPU output into an RGB image
+/// https://www.nesdev.org/wiki/NTSC_video
+pub struct NtscFilter {
+    preset: NtscPreset,
+    params: NtscParams,
+    /// Colors of the RGB preset
+    palette: Palette,
+    /// Subcarrier phase the current frame starts at, cycles to make the dots crawl
+    frame_phase: usize,
+    /// Demodulation angles of the 12 phases
+    angles: [(f64, f64); 12],
+    /// Signal of every 9-bit index at the 12 phases, followed by its mean which is the luma
+    signals: Vec<[f64; 13]>,
+    /// Gamma corrected channel values
+    gamma_table: Vec<u8>,
+    /// RGB24 image of the last filtered frame
+    output: Box<[u8]>,
+}
+
+impl NtscFilter {
+    pub fn new(preset: NtscPreset, params: NtscParams) -> NtscFilter {
+        let angles = std::array::from_fn(|phase| {
+            let angle = ntsc_demodulation_angle(phase, &params);
+            (angle.cos(), angle.sin())
+        });
+
+        let signals = (0..512)
+            .map(|idx| {
+                let mut signal: [f64; 13] =
+                    std::array::from_fn(|phase| ntsc_signal(idx, phase % 12));
+                signal[12] = signal[..12].iter().sum::<f64>() / 12.0;
+                signal
+            })
+            .collect();
+
+        NtscFilter {
+            preset,
+            params,
+            palette: Palette::generate_ntsc(&params),
+            frame_phase: 0,
+            angles,
+            signals,
+            gamma_table: (0..GAMMA_TABLE_SIZE)
+                .map(|val| gamma_correct(val as f64 / (GAMMA_TABLE_SIZE - 1) as f64, &params))
+                .collect(),
+            output: vec![0; NTSC_OUTPUT_WIDTH * INPUT_HEIGHT * 3].into_boxed_slice(),
+        }
+    }
+
+    /// Filters a frame of 9-bit palette indices (emphasis above the 6-bit index)
+    pub fn filter(&mut self, indices: &[u16]) {
+        // running sums of luma, I and Q, prefixed with 0 so any window is a subtraction
+        let mut sums = vec![(0.0, 0.0, 0.0); SAMPLES_PER_LINE + 1];
+
+        for (line, pixels) in indices.chunks_exact(INPUT_WIDTH).enumerate() {
+            let line_phase = (self.frame_phase + line * PHASE_SHIFT) % 12;
+            let out_line = &mut self.output[line * NTSC_OUTPUT_WIDTH * 3..];
+
+            if self.preset == NtscPreset::Rgb {
+                for x in 0..NTSC_OUTPUT_WIDTH {
+                    let idx = pixels[x * INPUT_WIDTH / NTSC_OUTPUT_WIDTH] as usize;
+                    let rgb = self.palette.color(idx & 0x3F, (idx >> 6) as u8);
+                    out_line[x * 3..x * 3 + 3].copy_from_slice(&rgb);
+                }
+                continue;
+            }
+
+            for sample in 0..SAMPLES_PER_LINE {
+                let phase = (line_phase + sample) % 12;
+                let signals = &self.signals[pixels[sample / SAMPLES_PER_PIXEL] as usize & 0x1FF];
+                let signal = signals[phase];
+
+                // S-Video carries luma on its own wire, the PPU's luma is the mean of the square wave
+                let (luma, chroma) = if self.preset == NtscPreset::SVideo {
+                    (signals[12], signal - signals[12])
+                } else {
+                    (signal, signal)
+                };
+
+                let (cos, sin) = self.angles[phase];
+                let (y, i, q) = sums[sample];
+                sums[sample + 1] = (y + luma, i + chroma * cos, q + chroma * sin);
+            }
+
+            let (luma_width, chroma_width) = self.preset.bandwidth();
+            let window = |center: usize, width: usize| {
+                let start = center.saturating_sub(width / 2);
+                let end = (center + width - width / 2).min(SAMPLES_PER_LINE);
+                (start, end)
+            };
+
+            for x in 0..NTSC_OUTPUT_WIDTH {
+                let center = (x * 2 + 1) * SAMPLES_PER_LINE / (NTSC_OUTPUT_WIDTH * 2);
+
+                // past the edges of the picture the signal is black
+                let (start, end) = window(center, luma_width);
+                let y = (sums[end].0 - sums[start].0) / luma_width as f64;
+
+                let (i, q) = if self.preset == NtscPreset::Monochrome {
+                    (0.0, 0.0)
+                } else {
+                    let (start, end) = window(center, chroma_width);
+                    (
+                        (sums[end].1 - sums[start].1) / chroma_width as f64,
+                        (sums[end].2 - sums[start].2) / chroma_width as f64,
+                    )
+                };
+
+                let rgb = yiq_to_linear(y, i, q, &self.params).map(|channel| {
+                    self.gamma_table[(channel * (GAMMA_TABLE_SIZE - 1) as f64).round() as usize]
+                });
+                out_line[x * 3..x * 3 + 3].copy_from_slice(&rgb);
+            }
+        }
+
+        // 3 frame phases like nes_ntsc's burst_phase
+        self.frame_phase = (self.frame_phase + PHASE_SHIFT) % 12;
+    }
+
+    /// RGB24 image of the last filtered frame, `NTSC_OUTPUT_WIDTH` pixels wide
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+}
@@ -0,0 +1,233 @@
+use std::{f64::consts::PI, fmt, fs, path::Path};
+
+/// Size of a .pal file with the 64 base colors
+const PALETTE_FILE_SIZE: usize = 64 * 3;
+
+/// Size of a .pal file with a copy of the 64 colors for each of the 8 emphasis combinations
+const EMPHASIS_PALETTE_FILE_SIZE: usize = 8 * PALETTE_FILE_SIZE;
+
+/// Attenuation of the channels that aren't emphasized, about 0.816 like the 2C02
+/// https://www.nesdev.org/wiki/NTSC_video#Color_Tint_Bits
+const EMPHASIS_ATTENUATION: (u16, u16) = (209, 256);
+
+/// Signal levels of the 2C02 in volts, low and high for each of the 4 luma levels
+/// https://www.nesdev.org/wiki/NTSC_video#Brightness_Levels
+const SIGNAL_LOW: [f64; 4] = [0.228, 0.312, 0.552, 0.880];
+const SIGNAL_HIGH: [f64; 4] = [0.616, 0.840, 1.100, 1.100];
+const SIGNAL_BLACK: f64 = SIGNAL_LOW[1];
+const SIGNAL_WHITE: f64 = SIGNAL_HIGH[3];
+
+/// Factor an emphasis bit scales the signal by during its phases
+const SIGNAL_EMPHASIS: f64 = 0.746;
+
+/// Phase offset in 1/12 cycles that lines hue 0 up with the colorburst
+const COLORBURST_PHASE: f64 = 4.0;
+
+const DEFAULT_PALETTE: [u8; PALETTE_FILE_SIZE] = [
+    84, 84, 84, 0, 30, 116, 8, 16, 144, 48, 0, 136, 68, 0, 100, 92, 0, 48, 84, 4, 0, 60, 24, 0, 32,
+    42, 0, 8, 58, 0, 0, 64, 0, 0, 60, 0, 0, 50, 60, 0, 0, 0, 0, 0, 0, 0, 0, 0, 152, 150, 152, 8,
+    76, 196, 48, 50, 236, 92, 30, 228, 136, 20, 176, 160, 20, 100, 152, 34, 32, 120, 60, 0, 84, 90,
+    0, 40, 114, 0, 8, 124, 0, 0, 118, 40, 0, 102, 120, 0, 0, 0, 0, 0, 0, 0, 0, 0, 236, 238, 236,
+    76, 154, 236, 120, 124, 236, 176, 98, 236, 228, 84, 236, 236, 88, 180, 236, 106, 100, 212, 136,
+    32, 160, 170, 0, 116, 196, 0, 76, 208, 32, 56, 204, 108, 56, 180, 204, 60, 60, 60, 0, 0, 0, 0,
+    0, 0, 236, 238, 236, 168, 204, 236, 188, 188, 236, 212, 178, 236, 236, 174, 236, 236, 174, 212,
+    236, 180, 176, 228, 196, 144, 204, 210, 120, 180, 222, 120, 168, 226, 144, 152, 226, 180, 160,
+    214, 228, 160, 162, 160, 0, 0, 0, 0, 0, 0,
+];
+
+#[derive(Debug)]
+pub enum PaletteError {
+    /// The file couldn't be read
+    Io(std::io::Error),
+
+    /// The file isn't 192 or 1536 bytes long
+    InvalidSize(usize),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::Io(err) => write!(f, "could not read palette: {}", err),
+            PaletteError::InvalidSize(size) => write!(
+                f,
+                "palette is {} bytes, expected {} or {}",
+                size, PALETTE_FILE_SIZE, EMPHASIS_PALETTE_FILE_SIZE
+            ),
+        }
+    }
+}
+
+/// Parameters of the NTSC palette generator
+#[derive(Debug, Clone, Copy)]
+pub struct NtscParams {
+    /// Hue rotation in degrees
+    pub hue: f64,
+
+    /// Chroma multiplier, 0 is greyscale
+    pub saturation: f64,
+
+    /// Multiplier of the whole signal
+    pub contrast: f64,
+
+    /// Gamma of the emulated TV, 2.2 leaves the signal levels as they are
+    pub gamma: f64,
+}
+
+impl Default for NtscParams {
+    fn default() -> Self {
+        NtscParams {
+            hue: 0.0,
+            saturation: 1.0,
+            contrast: 1.0,
+            gamma: 2.2,
+        }
+    }
+}
+
+/// Maps palette indices to RGB colors
+/// https://www.nesdev.org/wiki/PPU_palettes
+pub struct Palette {
+    /// RGB24 colors, either 64 or 512 with the emphasis variants
+    colors: Vec<u8>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            colors: DEFAULT_PALETTE.to_vec(),
+        }
+    }
+}
+
+impl Palette {
+    /// Parses a 192 or 1536 byte .pal file
+    pub fn from_bytes(data: &[u8]) -> Result<Palette, PaletteError> {
+        match data.len() {
+            PALETTE_FILE_SIZE | EMPHASIS_PALETTE_FILE_SIZE => Ok(Palette {
+                colors: data.to_vec(),
+            }),
+            size => Err(PaletteError::InvalidSize(size)),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Palette, PaletteError> {
+        Palette::from_bytes(&fs::read(path).map_err(PaletteError::Io)?)
+    }
+
+    /// Generates all 512 colors by decoding the composite signal the PPU would output
+    /// https://www.nesdev.org/wiki/NTSC_video
+    pub fn generate_ntsc(params: &NtscParams) -> Palette {
+        let colors = (0..EMPHASIS_PALETTE_FILE_SIZE / 3)
+            .flat_map(|idx| ntsc_color(idx, params))
+            .collect();
+
+        Palette { colors }
+    }
+
+    /// Returns the color of a 6-bit palette index with the PPUMASK emphasis bits (red, green, blue)
+    pub fn color(&self, idx: usize, emphasis: u8) -> [u8; 3] {
+        if self.colors.len() == EMPHASIS_PALETTE_FILE_SIZE {
+            let off = (emphasis as usize * 64 + idx) * 3;
+            return [self.colors[off], self.colors[off + 1], self.colors[off + 2]];
+        }
+
+        let off = idx * 3;
+        let mut rgb = [self.colors[off], self.colors[off + 1], self.colors[off + 2]];
+
+        // without emphasis variants, emphasizing a channel darkens the other two
+        let (num, den) = EMPHASIS_ATTENUATION;
+        let attenuate = |channel: u8| (channel as u16 * num / den) as u8;
+        for channel in 0..3 {
+            if emphasis & 1 << channel > 0 {
+                for (other, val) in rgb.iter_mut().enumerate() {
+                    if other != channel {
+                        *val = attenuate(*val);
+                    }
+                }
+            }
+        }
+
+        rgb
+    }
+}
+
+/// Normalized composite signal of a 9-bit palette index at one of the 12 subcarrier phases,
+/// 0 is black and 1 is white
+pub fn ntsc_signal(idx: usize, phase: usize) -> f64 {
+    let hue = idx & 0xF;
+    let emphasis = idx >> 6;
+
+    // columns $E and $F output black
+    let level = if hue >= 0xE { 1 } else { (idx >> 4) & 3 };
+    let low = if hue == 0 {
+        SIGNAL_HIGH[level]
+    } else {
+        SIGNAL_LOW[level]
+    };
+    let high = if hue >= 0xD {
+        SIGNAL_LOW[level]
+    } else {
+        SIGNAL_HIGH[level]
+    };
+
+    // the PPU outputs a square wave, high for 6 of the 12 phases of the color subcarrier
+    let in_phase = |color: usize| (color + phase) % 12 < 6;
+
+    let mut signal = if in_phase(hue) { high } else { low };
+
+    // each emphasis bit attenuates a different half of the subcarrier cycle
+    let emphasized = [0, 4, 8]
+        .iter()
+        .enumerate()
+        .any(|(bit, &color)| emphasis & 1 << bit > 0 && in_phase(color));
+    if emphasized {
+        signal *= SIGNAL_EMPHASIS;
+    }
+
+    (signal - SIGNAL_BLACK) / (SIGNAL_WHITE - SIGNAL_BLACK)
+}
+
+/// Angle the chroma of a subcarrier phase is demodulated at
+pub fn ntsc_demodulation_angle(phase: usize, params: &NtscParams) -> f64 {
+    PI * (phase as f64 + COLORBURST_PHASE + params.hue / 30.0) / 6.0
+}
+
+/// Converts a demodulated signal to RGB24 with the contrast, saturation and gamma adjustments
+pub fn yiq_to_rgb(y: f64, i: f64, q: f64, params: &NtscParams) -> [u8; 3] {
+    yiq_to_linear(y, i, q, params).map(|channel| gamma_correct(channel, params))
+}
+
+/// Converts a demodulated signal to RGB in the range 0.0-1.0 before gamma correction
+pub fn yiq_to_linear(y: f64, i: f64, q: f64, params: &NtscParams) -> [f64; 3] {
+    let y = y * params.contrast;
+    let i = i * params.saturation * params.contrast;
+    let q = q * params.saturation * params.contrast;
+
+    // https://en.wikipedia.org/wiki/YIQ#Preferred_1953_FCC_formula
+    let rgb = [
+        y + 0.956 * i + 0.621 * q,
+        y - 0.272 * i - 0.647 * q,
+        y - 1.106 * i + 1.703 * q,
+    ];
+
+    rgb.map(|channel| channel.clamp(0.0, 1.0))
+}
+
+/// Applies the gamma of the emulated TV to a channel in the range 0.0-1.0
+pub fn gamma_correct(channel: f64, params: &NtscParams) -> u8 {
+    (channel.powf(params.gamma / 2.2) * 255.0).round() as u8
+}
+
+/// Decodes one color of the 512 color palette, the emphasis bits are above the 6-bit index
+fn ntsc_color(idx: usize, params: &NtscParams) -> [u8; 3] {
+    let (mut y, mut i, mut q) = (0.0, 0.0, 0.0);
+    for phase in 0..12 {
+        let signal = ntsc_signal(idx, phase);
+        let angle = ntsc_demodulation_angle(phase, params);
+        y += signal;
+        i += signal * angle.cos();
+        q += signal * angle.sin();
+    }
+
+    yiq_to_rgb(y / 12.0, i / 12.0, q / 12.0, params)
+}
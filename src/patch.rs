@@ -0,0 +1,357 @@
+use std::{fmt, fs, io, path::Path};
+
+use crate::checksum::crc32;
+
+mod bps;
+mod ips;
+mod ups;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+/// Extensions checked next to the ROM when no patch is given explicitly
+const PATCH_EXTENSIONS: [&str; 3] = ["ips", "ups", "bps"];
+
+/// Largest file a patch may produce, far above any real ROM or disk image
+const MAX_TARGET_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum PatchError {
+    /// The patch doesn't start with a known magic
+    UnknownFormat,
+
+    /// The patch ended in the middle of a record
+    Truncated,
+
+    /// A size or offset in the patch is out of range
+    InvalidSize,
+
+    /// The input file is not the one the patch was made for
+    SourceChecksumMismatch { expected: u32, actual: u32 },
+
+    /// The patched file differs from the one the patch was made to produce
+    TargetChecksumMismatch { expected: u32, actual: u32 },
+
+    /// The patch file itself is corrupted
+    PatchChecksumMismatch { expected: u32, actual: u32 },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "unknown patch format"),
+            PatchError::Truncated => write!(f, "patch is truncated"),
+            PatchError::InvalidSize => write!(f, "patch contains an out of range size"),
+            PatchError::SourceChecksumMismatch { expected, actual } => write!(
+                f,
+                "source CRC32 mismatch: expected {:08X}, got {:08X}",
+                expected, actual
+            ),
+            PatchError::TargetChecksumMismatch { expected, actual } => write!(
+                f,
+                "target CRC32 mismatch: expected {:08X}, got {:08X}",
+                expected, actual
+            ),
+            PatchError::PatchChecksumMismatch { expected, actual } => write!(
+                f,
+                "patch CRC32 mismatch: expected {:08X}, got {:08X}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+/// Sequential reader over the patch data
+struct PatchReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> PatchReader<'a> {
+        PatchReader { data, pos }
+    }
+
+    fn read_u8(&mut self) -> Result<u8, PatchError> {
+        let val = *self.data.get(self.pos).ok_or(PatchError::Truncated)?;
+        self.pos += 1;
+        Ok(val)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        let end = self.pos.checked_add(len).ok_or(PatchError::Truncated)?;
+        let bytes = self.data.get(self.pos..end).ok_or(PatchError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u16_be(&mut self) -> Result<u16, PatchError> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u24_be(&mut self) -> Result<usize, PatchError> {
+        let bytes = self.read_bytes(3)?;
+        Ok((bytes[0] as usize) << 16 | (bytes[1] as usize) << 8 | bytes[2] as usize)
+    }
+
+    /// Reads a variable length number used by UPS and BPS
+    fn read_varint(&mut self) -> Result<usize, PatchError> {
+        let mut val: usize = 0;
+        let mut shift: usize = 1;
+
+        loop {
+            let byte = self.read_u8()?;
+            val = ((byte & 0x7F) as usize)
+                .checked_mul(shift)
+                .and_then(|digit| val.checked_add(digit))
+                .ok_or(PatchError::InvalidSize)?;
+            if byte & 0x80 > 0 {
+                break;
+            }
+
+            shift = shift.checked_mul(0x80).ok_or(PatchError::InvalidSize)?;
+            val = val.checked_add(shift).ok_or(PatchError::InvalidSize)?;
+        }
+
+        Ok(val)
+    }
+
+    /// Reads the size of the patched file, refusing sizes no ROM comes close to
+    fn read_target_size(&mut self) -> Result<usize, PatchError> {
+        let size = self.read_varint()?;
+        if size > MAX_TARGET_SIZE {
+            return Err(PatchError::InvalidSize);
+        }
+
+        Ok(size)
+    }
+}
+
+/// The checksums stored in the last 12 bytes of UPS and BPS patches
+struct PatchFooter {
+    source_crc: u32,
+    target_crc: u32,
+}
+
+/// Validates the patch checksum and returns the footer and the length of the patch body
+fn read_footer(patch: &[u8]) -> Result<(PatchFooter, usize), PatchError> {
+    if patch.len() < 12 {
+        return Err(PatchError::Truncated);
+    }
+
+    let body_len = patch.len() - 12;
+    let read_crc = |off: usize| u32::from_le_bytes(patch[off..off + 4].try_into().unwrap());
+
+    let expected = read_crc(body_len + 8);
+    let actual = crc32(&patch[..body_len + 8]);
+    if expected != actual {
+        return Err(PatchError::PatchChecksumMismatch { expected, actual });
+    }
+
+    let footer = PatchFooter {
+        source_crc: read_crc(body_len),
+        target_crc: read_crc(body_len + 4),
+    };
+
+    Ok((footer, body_len))
+}
+
+fn check_source(footer: &PatchFooter, source: &[u8]) -> Result<(), PatchError> {
+    let actual = crc32(source);
+    if actual != footer.source_crc {
+        return Err(PatchError::SourceChecksumMismatch {
+            expected: footer.source_crc,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_target(footer: &PatchFooter, target: &[u8]) -> Result<(), PatchError> {
+    let actual = crc32(target);
+    if actual != footer.target_crc {
+        return Err(PatchError::TargetChecksumMismatch {
+            expected: footer.target_crc,
+            actual,
+        });
+    }
+
+    Ok(())
+}
+
+/// Applies an IPS, UPS or BPS patch, the format is detected from the magic
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(IPS_MAGIC) {
+        ips::apply(rom, patch)
+    } else if patch.starts_with(UPS_MAGIC) {
+        ups::apply(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        bps::apply(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+/// Creates an IPS patch between two files of the same size
+pub fn create_ips_patch(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    ips::create(original, modified)
+}
+
+/// Looks for a patch with the same name as the ROM(game.nes -> game.ips/.ups/.bps),
+/// a patch that exists but can't be read is an error
+pub fn find_patch(rom_path: &Path) -> io::Result<Option<Vec<u8>>> {
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|ext| rom_path.with_extension(ext))
+        .find(|path| path.is_file())
+        .map(fs::read)
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::crc32;
+
+    use super::{apply_patch, create_ips_patch, PatchError, BPS_MAGIC, UPS_MAGIC};
+
+    /// Inverse of `PatchReader::read_varint`
+    fn varint(mut val: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let digit = (val & 0x7F) as u8;
+            val >>= 7;
+            if val == 0 {
+                out.push(digit | 0x80);
+                return out;
+            }
+            out.push(digit);
+            val -= 1;
+        }
+    }
+
+    /// Appends the source, target and patch checksums used by UPS and BPS
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn ips_round_trip() {
+        let original = vec![0; 0x100];
+        let mut modified = original.clone();
+        modified[0x10..0x20].fill(0xAA);
+        modified[0xFF] = 1;
+
+        let patch = create_ips_patch(&original, &modified);
+
+        assert_eq!(apply_patch(&original, &patch).unwrap(), modified);
+    }
+
+    #[test]
+    fn ips_change_at_the_eof_offset() {
+        let original = vec![0; 0x454F50];
+        let mut modified = original.clone();
+        modified[0x454F46] = 1;
+
+        let patch = create_ips_patch(&original, &modified);
+
+        assert_eq!(apply_patch(&original, &patch).unwrap(), modified);
+    }
+
+    #[test]
+    fn ips_rle_record_and_truncation() {
+        let mut patch = b"PATCH".to_vec();
+        // fill 3 bytes at 2 with $AA, growing the file to 5 bytes
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0xAA]);
+        patch.extend_from_slice(b"EOF");
+        // truncate to 4 bytes
+        patch.extend_from_slice(&[0x00, 0x00, 0x04]);
+
+        let out = apply_patch(&[1, 2, 3], &patch).unwrap();
+
+        assert_eq!(out, [1, 2, 0xAA, 0xAA]);
+    }
+
+    #[test]
+    fn ips_truncated_record() {
+        let patch = b"PATCH\x00\x00\x02\x00\x04\x01".to_vec();
+
+        assert!(matches!(
+            apply_patch(&[0; 8], &patch),
+            Err(PatchError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn ups_xor_hunks() {
+        let source = [1, 2, 3, 4];
+        let target = [1, 2, 0x13, 4, 5];
+
+        let mut body = UPS_MAGIC.to_vec();
+        body.extend(varint(source.len()));
+        body.extend(varint(target.len()));
+        // skip 2 bytes, xor one
+        body.extend(varint(2));
+        body.extend_from_slice(&[0x03 ^ 0x13, 0x00]);
+        // the byte after the terminator, past the end of the source
+        body.extend(varint(0));
+        body.extend_from_slice(&[0x05, 0x00]);
+        let patch = with_footer(body, &source, &target);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+        assert!(matches!(
+            apply_patch(&[0; 4], &patch),
+            Err(PatchError::SourceChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn bps_actions() {
+        let source = [1, 2, 3, 4];
+        let target = [1, 2, 9, 9, 9, 4];
+
+        let mut body = BPS_MAGIC.to_vec();
+        body.extend(varint(source.len()));
+        body.extend(varint(target.len()));
+        body.extend(varint(0));
+        // source read of 2
+        body.extend(varint(1 << 2));
+        // target read of 1
+        body.extend(varint(1));
+        body.push(9);
+        // target copy of 2 from 2, overlapping what it writes
+        body.extend(varint(1 << 2 | 3));
+        body.extend(varint(2 << 1));
+        // source copy of 1 from 3
+        body.extend(varint(2));
+        body.extend(varint(3 << 1));
+        let patch = with_footer(body, &source, &target);
+
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn corrupted_patch() {
+        let mut patch = with_footer(UPS_MAGIC.to_vec(), &[], &[]);
+        patch[0] = b'X';
+
+        assert!(matches!(
+            apply_patch(&[], &patch),
+            Err(PatchError::UnknownFormat)
+        ));
+
+        let mut patch = with_footer(BPS_MAGIC.to_vec(), &[], &[]);
+        let last = patch.len() - 1;
+        patch[last] ^= 1;
+
+        assert!(matches!(
+            apply_patch(&[], &patch),
+            Err(PatchError::PatchChecksumMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+use super::{check_source, check_target, read_footer, PatchError, PatchReader, BPS_MAGIC};
+
+const SOURCE_READ: usize = 0;
+const TARGET_READ: usize = 1;
+const SOURCE_COPY: usize = 2;
+const TARGET_COPY: usize = 3;
+
+/// Applies a signed relative offset encoded as (magnitude << 1 | sign)
+fn apply_relative(base: usize, data: usize) -> Result<usize, PatchError> {
+    let magnitude = data >> 1;
+    if data & 1 > 0 {
+        base.checked_sub(magnitude).ok_or(PatchError::Truncated)
+    } else {
+        base.checked_add(magnitude).ok_or(PatchError::InvalidSize)
+    }
+}
+
+// https://www.romhacking.net/documents/746/
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (footer, body_len) = read_footer(patch)?;
+    check_source(&footer, rom)?;
+
+    let mut reader = PatchReader::new(&patch[..body_len], BPS_MAGIC.len());
+
+    let _source_size = reader.read_varint()?;
+    let target_size = reader.read_target_size()?;
+    let metadata_size = reader.read_varint()?;
+    reader.read_bytes(metadata_size)?;
+
+    let mut out: Vec<u8> = Vec::with_capacity(target_size);
+    let mut source_rel = 0;
+    let mut target_rel = 0;
+
+    while reader.pos < body_len {
+        let data = reader.read_varint()?;
+        let len = (data >> 2) + 1;
+
+        // every action appends to the output, which can't grow past the target size
+        if out.len() + len > target_size {
+            return Err(PatchError::InvalidSize);
+        }
+
+        match data & 0b11 {
+            SOURCE_READ => {
+                let off = out.len();
+                let bytes = rom.get(off..off + len).ok_or(PatchError::Truncated)?;
+                out.extend_from_slice(bytes);
+            }
+            TARGET_READ => {
+                let bytes = reader.read_bytes(len)?;
+                out.extend_from_slice(bytes);
+            }
+            SOURCE_COPY => {
+                source_rel = apply_relative(source_rel, reader.read_varint()?)?;
+                let bytes = rom
+                    .get(source_rel..source_rel.saturating_add(len))
+                    .ok_or(PatchError::Truncated)?;
+                out.extend_from_slice(bytes);
+                source_rel += len;
+            }
+            TARGET_COPY => {
+                target_rel = apply_relative(target_rel, reader.read_varint()?)?;
+                // the regions may overlap so it has to be copied byte by byte
+                for _ in 0..len {
+                    let byte = *out.get(target_rel).ok_or(PatchError::Truncated)?;
+                    out.push(byte);
+                    target_rel += 1;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    check_target(&footer, &out)?;
+
+    Ok(out)
+}
@@ -0,0 +1,81 @@
+use super::{PatchError, PatchReader, IPS_MAGIC};
+
+const EOF_MARKER: usize = 0x454F46;
+
+// https://zerosoft.zophar.net/ips.php
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut out = Vec::from(rom);
+    let mut reader = PatchReader::new(patch, IPS_MAGIC.len());
+
+    loop {
+        let offset = reader.read_u24_be()?;
+        if offset == EOF_MARKER {
+            break;
+        }
+
+        let size = reader.read_u16_be()? as usize;
+        if size == 0 {
+            // RLE record
+            let count = reader.read_u16_be()? as usize;
+            let val = reader.read_u8()?;
+
+            if out.len() < offset + count {
+                out.resize(offset + count, 0);
+            }
+            out[offset..offset + count].fill(val);
+        } else {
+            let data = reader.read_bytes(size)?;
+
+            if out.len() < offset + size {
+                out.resize(offset + size, 0);
+            }
+            out[offset..offset + size].copy_from_slice(data);
+        }
+    }
+
+    // optional truncation extension
+    if let Ok(truncate) = reader.read_u24_be() {
+        out.truncate(truncate);
+    }
+
+    Ok(out)
+}
+
+/// Maximum length of a single record
+const MAX_RECORD_SIZE: usize = u16::MAX as usize;
+
+/// Creates an IPS patch turning `original` into `modified`, both have to be the same size
+pub fn create(original: &[u8], modified: &[u8]) -> Vec<u8> {
+    assert_eq!(original.len(), modified.len());
+
+    let mut patch = Vec::from(IPS_MAGIC);
+
+    let mut off = 0;
+    while off < modified.len() {
+        if original[off] == modified[off] {
+            off += 1;
+            continue;
+        }
+
+        // an offset spelling out "EOF" would be read as the end marker
+        let start = if off == EOF_MARKER { off - 1 } else { off };
+
+        let mut end = off;
+        while end < modified.len()
+            && end - start < MAX_RECORD_SIZE
+            && original[end] != modified[end]
+        {
+            end += 1;
+        }
+
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&modified[start..end]);
+
+        off = end;
+    }
+
+    patch.extend_from_slice(&(EOF_MARKER as u32).to_be_bytes()[1..]);
+
+    patch
+}
@@ -0,0 +1,39 @@
+use super::{check_source, check_target, read_footer, PatchError, PatchReader, UPS_MAGIC};
+
+// https://www.romhacking.net/documents/392/
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (footer, body_len) = read_footer(patch)?;
+    check_source(&footer, rom)?;
+
+    let mut reader = PatchReader::new(&patch[..body_len], UPS_MAGIC.len());
+
+    let _source_size = reader.read_varint()?;
+    let target_size = reader.read_target_size()?;
+
+    let mut out = Vec::from(rom);
+    out.resize(target_size, 0);
+
+    let mut off: usize = 0;
+    while reader.pos < body_len {
+        off = off
+            .checked_add(reader.read_varint()?)
+            .ok_or(PatchError::InvalidSize)?;
+
+        loop {
+            let xor = reader.read_u8()?;
+            if xor == 0 {
+                off += 1;
+                break;
+            }
+
+            if let Some(byte) = out.get_mut(off) {
+                *byte ^= xor;
+            }
+            off += 1;
+        }
+    }
+
+    check_target(&footer, &out)?;
+
+    Ok(out)
+}
@@ -0,0 +1,93 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Region {
+    #[default]
+    Ntsc,
+    Pal,
+    /// Famiclone timing, PAL frame rate with an NTSC-like CPU:PPU ratio
+    Dendy,
+}
+
+impl Region {
+    /// Parses the name passed on the command line
+    pub fn from_name(name: &str) -> Option<Region> {
+        match name.to_ascii_lowercase().as_str() {
+            "ntsc" => Some(Region::Ntsc),
+            "pal" => Some(Region::Pal),
+            "dendy" => Some(Region::Dendy),
+            _ => None,
+        }
+    }
+
+    /// Name shown to the user
+    pub fn name(&self) -> &'static str {
+        match self {
+            Region::Ntsc => "NTSC",
+            Region::Pal => "PAL",
+            Region::Dendy => "Dendy",
+        }
+    }
+
+    /// PPU dots per CPU cycle as a fraction(numerator, denominator)
+    pub fn ppu_cpu_ratio(&self) -> (usize, usize) {
+        match self {
+            Region::Ntsc | Region::Dendy => (3, 1),
+            Region::Pal => (16, 5),
+        }
+    }
+
+    /// CPU clock rate in Hz
+    pub fn cpu_clock_rate(&self) -> f64 {
+        match self {
+            Region::Ntsc => 1_789_773.0,
+            Region::Pal => 1_662_607.0,
+            Region::Dendy => 1_773_448.0,
+        }
+    }
+
+    /// Number of scanlines including the pre-render line
+    pub fn scanlines(&self) -> usize {
+        match self {
+            Region::Ntsc => 262,
+            Region::Pal | Region::Dendy => 312,
+        }
+    }
+
+    /// The last scanline of the frame
+    pub fn prerender_scanline(&self) -> usize {
+        self.scanlines() - 1
+    }
+
+    /// Whether odd frames skip the last dot of the pre-render line while rendering
+    pub fn skips_odd_frame_dot(&self) -> bool {
+        matches!(self, Region::Ntsc)
+    }
+
+    /// Whether the PPUMASK red and green emphasis bits are swapped
+    /// https://www.nesdev.org/wiki/PPU_registers#Color_control
+    pub fn swaps_red_green_emphasis(&self) -> bool {
+        matches!(self, Region::Pal | Region::Dendy)
+    }
+
+    /// The scanline vertical blanking starts at
+    pub fn vblank_scanline(&self) -> usize {
+        match self {
+            Region::Ntsc | Region::Pal => 241,
+            // Dendy delays vblank by 50 scanlines to stay compatible with NTSC games
+            Region::Dendy => 291,
+        }
+    }
+
+    /// Frames per second
+    pub fn frame_rate(&self) -> f64 {
+        let (ppu_cycles, cpu_cycles) = self.ppu_cpu_ratio();
+        let dots_per_frame = (self.scanlines() * 341) as f64;
+        let ppu_clock_rate = self.cpu_clock_rate() * ppu_cycles as f64 / cpu_cycles as f64;
+
+        ppu_clock_rate / dots_per_frame
+    }
+
+    /// Length of a frame in nanoseconds
+    pub fn frame_time(&self) -> u128 {
+        (1_000_000_000.0 / self.frame_rate()) as u128
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum StateError {
+    /// The data doesn't start with the save state magic
+    InvalidFormat,
+
+    /// The state was saved by an incompatible version
+    UnsupportedVersion(u32),
+
+    /// The state was saved with a different ROM
+    RomMismatch { expected: u32, actual: u32 },
+
+    /// The state ended unexpectedly or contains invalid values
+    Corrupted,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::InvalidFormat => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => {
+                write!(f, "unsupported save state version {}", version)
+            }
+            StateError::RomMismatch { expected, actual } => write!(
+                f,
+                "save state is for a different ROM: expected CRC32 {:08X}, got {:08X}",
+                expected, actual
+            ),
+            StateError::Corrupted => write!(f, "save state is corrupted"),
+        }
+    }
+}
+
+/// Serializes state into a little endian binary blob
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        StateWriter { buf: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn write_u8(&mut self, val: u8) {
+        self.buf.push(val);
+    }
+
+    pub fn write_bool(&mut self, val: bool) {
+        self.buf.push(val.into());
+    }
+
+    pub fn write_u16(&mut self, val: u16) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, val: u32) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, val: u64) {
+        self.buf.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, val: usize) {
+        self.write_u64(val as u64);
+    }
+
+    /// Writes bytes without a length, the reader has to know the size
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Writes bytes prefixed with their length
+    pub fn write_blob(&mut self, bytes: &[u8]) {
+        self.write_usize(bytes.len());
+        self.write_bytes(bytes);
+    }
+}
+
+/// Deserializes state written by `StateWriter`
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> StateReader<'a> {
+        StateReader { data, pos: 0 }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(StateError::Corrupted)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        Ok(self.read_bytes(N)?.try_into().unwrap())
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.read_u8()? > 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, StateError> {
+        Ok(self.read_u64()? as usize)
+    }
+
+    /// Reads exactly `buf.len()` bytes into `buf`
+    pub fn read_into(&mut self, buf: &mut [u8]) -> Result<(), StateError> {
+        buf.copy_from_slice(self.read_bytes(buf.len())?);
+        Ok(())
+    }
+
+    /// Reads bytes written by `write_blob`
+    pub fn read_blob(&mut self) -> Result<&'a [u8], StateError> {
+        let len = self.read_usize()?;
+        self.read_bytes(len)
+    }
+
+    /// Reads a blob into a buffer which has to be the same size
+    pub fn read_blob_into(&mut self, buf: &mut [u8]) -> Result<(), StateError> {
+        let blob = self.read_blob()?;
+        if blob.len() != buf.len() {
+            return Err(StateError::Corrupted);
+        }
+
+        buf.copy_from_slice(blob);
+        Ok(())
+    }
+}
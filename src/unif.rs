@@ -0,0 +1,214 @@
+use crate::{
+    nes::{MirroringMode, NESFile},
+    region::Region,
+};
+
+const UNIF_MAGIC: [u8; 4] = *b"UNIF";
+
+/// Size of the UNIF header(magic, revision and padding)
+const HEADER_SIZE: usize = 32;
+
+/// Size of a chunk header(ID and length)
+const CHUNK_HEADER_SIZE: usize = 8;
+
+/// Prefixes that only describe the manufacturer of the board
+const BOARD_PREFIXES: [&str; 6] = ["NES-", "HVC-", "UNL-", "BTL-", "BMC-", "IREM-"];
+
+/// Board names and the mapper numbers implementing them
+const BOARDS: [(&str, u8); 6] = [
+    ("NROM", 0),
+    ("NROM-128", 0),
+    ("NROM-256", 0),
+    ("RROM", 0),
+    ("RROM-128", 0),
+    ("SROM", 0),
+];
+
+/// Returns whether the file starts with the UNIF magic
+pub fn is_unif_file(file: &[u8]) -> bool {
+    file.starts_with(&UNIF_MAGIC)
+}
+
+/// Maps a UNIF board name to a mapper number
+fn board_to_mapper(board: &str) -> Option<u8> {
+    let name = BOARD_PREFIXES
+        .iter()
+        .find_map(|prefix| board.strip_prefix(prefix))
+        .unwrap_or(board);
+
+    BOARDS
+        .iter()
+        .find(|(board_name, _)| *board_name == name)
+        .map(|(_, mapper)| *mapper)
+}
+
+/// Index of a PRG0-PRGF or CHR0-CHRF chunk
+fn chunk_index(id: &[u8]) -> Option<usize> {
+    char::from(id[3]).to_digit(16).map(|idx| idx as usize)
+}
+
+// https://www.nesdev.org/wiki/UNIF
+pub fn parse_unif_file(file: &[u8]) -> Result<NESFile, ()> {
+    if !is_unif_file(file) || file.len() < HEADER_SIZE {
+        return Err(());
+    }
+
+    let mut board = None;
+    let mut mirroring_mode = MirroringMode::Horizontal;
+    let mut has_prg_ram = false;
+    let mut region = None;
+    let mut prg_chunks: [Option<&[u8]>; 16] = [None; 16];
+    let mut chr_chunks: [Option<&[u8]>; 16] = [None; 16];
+
+    let mut off = HEADER_SIZE;
+    while off + CHUNK_HEADER_SIZE <= file.len() {
+        let id = &file[off..off + 4];
+        let len = u32::from_le_bytes(file[off + 4..off + 8].try_into().unwrap()) as usize;
+
+        let data_start = off + CHUNK_HEADER_SIZE;
+        let data = file.get(data_start..data_start + len).ok_or(())?;
+        off = data_start + len;
+
+        match id {
+            b"MAPR" => {
+                let name_len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+                board = Some(String::from_utf8_lossy(&data[..name_len]).into_owned());
+            }
+            b"MIRR" => {
+                mirroring_mode = match data.first() {
+                    Some(0) => MirroringMode::Horizontal,
+                    Some(1) => MirroringMode::Vertical,
+                    Some(2) => MirroringMode::SingleScreenLower,
+                    Some(3) => MirroringMode::SingleScreenUpper,
+                    Some(4) => MirroringMode::FourScreen,
+                    // mapper controlled
+                    _ => MirroringMode::Horizontal,
+                };
+            }
+            b"BATR" => has_prg_ram = true,
+            b"TVCI" => {
+                region = match data.first() {
+                    Some(1) => Some(Region::Pal),
+                    // NTSC or both
+                    Some(_) => Some(Region::Ntsc),
+                    None => None,
+                };
+            }
+            _ if id.starts_with(b"PRG") => {
+                prg_chunks[chunk_index(id).ok_or(())?] = Some(data);
+            }
+            _ if id.starts_with(b"CHR") => {
+                chr_chunks[chunk_index(id).ok_or(())?] = Some(data);
+            }
+            // other chunks(NAME, READ, DINF, CTRL, PCK0, CCK0, ...) are informational
+            _ => {}
+        }
+    }
+
+    let board = board.ok_or(())?;
+    let mapper_number = match board_to_mapper(&board) {
+        Some(mapper) => mapper,
+        None => {
+            eprintln!("Unsupported UNIF board: {}", board);
+            return Err(());
+        }
+    };
+
+    let prg_rom: Vec<u8> = prg_chunks
+        .iter()
+        .flatten()
+        .flat_map(|c| c.iter())
+        .copied()
+        .collect();
+    let chr_rom: Vec<u8> = chr_chunks
+        .iter()
+        .flatten()
+        .flat_map(|c| c.iter())
+        .copied()
+        .collect();
+
+    if prg_rom.is_empty() {
+        return Err(());
+    }
+
+    let nes = NESFile {
+        mirroring_mode,
+        has_prg_ram,
+        has_trainer: false,
+        mapper_number,
+        prg_rom,
+        chr_rom,
+        disk_sides: Vec::new(),
+        nsf: None,
+        region,
+    };
+
+    Ok(nes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{nes::MirroringMode, region::Region};
+
+    use super::{parse_unif_file, HEADER_SIZE, UNIF_MAGIC};
+
+    /// Builds a UNIF file out of (ID, data) chunks
+    fn unif(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut file = UNIF_MAGIC.to_vec();
+        file.resize(HEADER_SIZE, 0);
+
+        for (id, data) in chunks {
+            file.extend_from_slice(*id);
+            file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            file.extend_from_slice(data);
+        }
+
+        file
+    }
+
+    #[test]
+    fn parses_an_nrom_board() {
+        let file = unif(&[
+            (b"MAPR", b"NES-NROM-256\0"),
+            (b"MIRR", &[1]),
+            (b"TVCI", &[1]),
+            (b"BATR", &[0]),
+            (b"PRG1", &[2; 4]),
+            (b"PRG0", &[1; 4]),
+            (b"CHR0", &[3; 8]),
+            (b"NAME", b"Test\0"),
+        ]);
+
+        let nes = parse_unif_file(&file).unwrap();
+
+        assert_eq!(nes.mapper_number, 0);
+        assert!(matches!(nes.mirroring_mode, MirroringMode::Vertical));
+        assert_eq!(nes.region, Some(Region::Pal));
+        assert!(nes.has_prg_ram);
+        // chunks are ordered by their index, not by their position in the file
+        assert_eq!(nes.prg_rom, [1, 1, 1, 1, 2, 2, 2, 2]);
+        assert_eq!(nes.chr_rom, [3; 8]);
+    }
+
+    #[test]
+    fn rejects_a_truncated_chunk() {
+        let mut file = unif(&[(b"MAPR", b"NROM\0"), (b"PRG0", &[0; 16])]);
+        file.truncate(file.len() - 1);
+
+        assert!(parse_unif_file(&file).is_err());
+    }
+
+    #[test]
+    fn rejects_an_unsupported_board() {
+        let file = unif(&[(b"MAPR", b"NES-TLROM\0"), (b"PRG0", &[0; 16])]);
+
+        assert!(parse_unif_file(&file).is_err());
+    }
+
+    #[test]
+    fn rejects_a_file_without_prg_rom() {
+        let file = unif(&[(b"MAPR", b"NROM\0"), (b"CHR0", &[0; 16])]);
+
+        assert!(parse_unif_file(&file).is_err());
+    }
+}